@@ -0,0 +1,268 @@
+use std::sync::Arc;
+
+// Parses text into tree of units, which can be removed as a whole, without breaking balance of brackets
+// - unit ends at `\n`, `;` or `,` which is outside of any bracket
+// - content between balanced `{}`, `()` and `[]` inside unit, is parsed into child units
+// - lines with bigger indentation directly after unit(e.g. python function body) belong to it and are also parsed into child units
+// Brackets inside strings and comments are not recognized, so in such cases tree may be not accurate
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Unit {
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    pub(crate) children: Vec<Unit>,
+}
+
+pub fn parse_units(chars: &[char]) -> Vec<Unit> {
+    parse_region(chars, 0, chars.len())
+}
+
+fn parse_region(chars: &[char], start: usize, end: usize) -> Vec<Unit> {
+    let mut units = Vec::new();
+    let mut unit_start = start;
+    let mut open_brackets: Vec<(char, usize)> = Vec::new();
+    let mut regions: Vec<(usize, usize)> = Vec::new();
+
+    let mut idx = start;
+    while idx < end {
+        let character = chars[idx];
+        match character {
+            '{' | '(' | '[' => open_brackets.push((character, idx)),
+            '}' | ')' | ']' => {
+                if let Some(&(open_character, open_idx)) = open_brackets.last() {
+                    if closing_bracket(open_character) == character {
+                        open_brackets.pop();
+                        if open_brackets.is_empty() {
+                            regions.push((open_idx + 1, idx));
+                        }
+                    }
+                }
+            }
+            '\n' | ';' | ',' if open_brackets.is_empty() => {
+                let mut unit_end = idx + 1;
+                if character == '\n' {
+                    let body_end = find_indented_body_end(chars, unit_start, unit_end, end);
+                    if body_end > unit_end {
+                        regions.push((unit_end, body_end));
+                        unit_end = body_end;
+                    }
+                }
+                push_unit(chars, &mut units, unit_start, unit_end, &regions);
+                regions.clear();
+                unit_start = unit_end;
+                idx = unit_end;
+                continue;
+            }
+            _ => {}
+        }
+        idx += 1;
+    }
+    push_unit(chars, &mut units, unit_start, end, &regions);
+
+    units
+}
+
+fn push_unit(chars: &[char], units: &mut Vec<Unit>, start: usize, end: usize, regions: &[(usize, usize)]) {
+    // Units with only whitespaces are not worth removing, so they are left as part of fixed text
+    if chars[start..end].iter().all(|character| character.is_whitespace()) {
+        return;
+    }
+    let children = regions
+        .iter()
+        .flat_map(|&(region_start, region_end)| parse_region(chars, region_start, region_end))
+        .collect();
+    units.push(Unit { start, end, children });
+}
+
+fn closing_bracket(open_character: char) -> char {
+    match open_character {
+        '{' => '}',
+        '(' => ')',
+        '[' => ']',
+        _ => unreachable!(),
+    }
+}
+
+// Returns end of lines after unit with bigger indentation than unit itself, or header_end when there is no such lines
+fn find_indented_body_end(chars: &[char], unit_start: usize, header_end: usize, region_end: usize) -> usize {
+    let Some(first_visible) = (unit_start..header_end).find(|&idx| !chars[idx].is_whitespace()) else {
+        return header_end;
+    };
+    let header_indentation = line_indentation(chars, line_start(chars, first_visible));
+
+    let mut body_end = header_end;
+    let mut current_line_start = header_end;
+    while current_line_start < region_end {
+        let current_line_end = (current_line_start..region_end)
+            .find(|&idx| chars[idx] == '\n')
+            .map_or(region_end, |idx| idx + 1);
+        let line = &chars[current_line_start..current_line_end];
+        if !line.iter().all(|character| character.is_whitespace()) {
+            if line_indentation(chars, current_line_start) <= header_indentation {
+                break;
+            }
+            body_end = current_line_end;
+        }
+        current_line_start = current_line_end;
+    }
+    body_end
+}
+
+fn line_start(chars: &[char], idx: usize) -> usize {
+    chars[..idx]
+        .iter()
+        .rposition(|character| *character == '\n')
+        .map_or(0, |position| position + 1)
+}
+
+fn line_indentation(chars: &[char], line_start: usize) -> usize {
+    chars[line_start..]
+        .iter()
+        .take_while(|character| **character == ' ' || **character == '\t')
+        .count()
+}
+
+pub fn count_levels(units: &[Unit]) -> usize {
+    units
+        .iter()
+        .map(|unit| 1 + count_levels(&unit.children))
+        .max()
+        .unwrap_or(0)
+}
+
+fn collect_level_ranges(units: &[Unit], level: usize, ranges: &mut Vec<(usize, usize)>) {
+    for unit in units {
+        if level == 0 {
+            ranges.push((unit.start, unit.end));
+        } else {
+            collect_level_ranges(&unit.children, level - 1, ranges);
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Segment {
+    Fixed(String),
+    Removable(String),
+}
+
+// Whole text split into parts, which can be removed at current level and parts which must stay untouched
+#[derive(Debug)]
+pub struct BlockLayout {
+    segments: Vec<Segment>,
+}
+
+impl BlockLayout {
+    // Returns layout and number of removable blocks at given level
+    pub fn new(chars: &[char], units: &[Unit], level: usize) -> (Arc<BlockLayout>, usize) {
+        let mut ranges = Vec::new();
+        collect_level_ranges(units, level, &mut ranges);
+
+        let mut segments = Vec::new();
+        let mut current_idx = 0;
+        for &(start, end) in &ranges {
+            if current_idx < start {
+                segments.push(Segment::Fixed(chars[current_idx..start].iter().collect()));
+            }
+            segments.push(Segment::Removable(chars[start..end].iter().collect()));
+            current_idx = end;
+        }
+        if current_idx < chars.len() {
+            segments.push(Segment::Fixed(chars[current_idx..].iter().collect()));
+        }
+
+        (Arc::new(BlockLayout { segments }), ranges.len())
+    }
+
+    // Indexes of blocks must be sorted, which is always true, because rules only remove items, without changing order
+    pub fn create_text(&self, blocks_indexes: impl Iterator<Item = usize>) -> String {
+        let mut blocks_indexes = blocks_indexes.peekable();
+        let mut text = String::new();
+        let mut removable_idx = 0;
+        for segment in &self.segments {
+            match segment {
+                Segment::Fixed(fixed) => text.push_str(fixed),
+                Segment::Removable(removable) => {
+                    if blocks_indexes.peek() == Some(&removable_idx) {
+                        blocks_indexes.next();
+                        text.push_str(removable);
+                    }
+                    removable_idx += 1;
+                }
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts_at_level(text: &str, level: usize) -> Vec<String> {
+        let chars = text.chars().collect::<Vec<_>>();
+        let mut ranges = Vec::new();
+        collect_level_ranges(&parse_units(&chars), level, &mut ranges);
+        ranges
+            .into_iter()
+            .map(|(start, end)| chars[start..end].iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_brackets_tree() {
+        let text = "fn a() {\n    x;\n    y;\n}\nfn b() {}\n";
+        assert_eq!(
+            texts_at_level(text, 0),
+            vec!["fn a() {\n    x;\n    y;\n}\n", "fn b() {}\n"]
+        );
+        assert_eq!(texts_at_level(text, 1), vec!["    x;", "    y;"]);
+        assert!(texts_at_level(text, 2).is_empty());
+
+        let chars = text.chars().collect::<Vec<_>>();
+        assert_eq!(count_levels(&parse_units(&chars)), 2);
+    }
+
+    #[test]
+    fn test_arguments_split() {
+        assert_eq!(texts_at_level("call(a, b(c, d));", 0), vec!["call(a, b(c, d));"]);
+        assert_eq!(texts_at_level("call(a, b(c, d));", 1), vec!["a,", " b(c, d)"]);
+        assert_eq!(texts_at_level("call(a, b(c, d));", 2), vec!["c,", " d"]);
+    }
+
+    #[test]
+    fn test_indentation_tree() {
+        let text = "def a():\n    x = 1\n\n    if x:\n        y\nz = 2\n";
+        assert_eq!(
+            texts_at_level(text, 0),
+            vec!["def a():\n    x = 1\n\n    if x:\n        y\n", "z = 2\n"]
+        );
+        assert_eq!(texts_at_level(text, 1), vec!["    x = 1\n", "    if x:\n        y\n"]);
+        assert_eq!(texts_at_level(text, 2), vec!["        y\n"]);
+    }
+
+    #[test]
+    fn test_unbalanced_brackets() {
+        assert_eq!(texts_at_level("a)\nb(\nc\n", 0), vec!["a)\n", "b(\nc\n"]);
+        assert!(texts_at_level("a)\nb(\nc\n", 1).is_empty());
+    }
+
+    #[test]
+    fn test_layout_create_text() {
+        let text = "fn a() {\n    x;\n    y;\n}\nfn b() {}\n";
+        let chars = text.chars().collect::<Vec<_>>();
+        let units = parse_units(&chars);
+
+        let (layout, blocks_number) = BlockLayout::new(&chars, &units, 1);
+        assert_eq!(blocks_number, 2);
+        assert_eq!(layout.create_text(0..2), text);
+        assert_eq!(
+            layout.create_text([1].into_iter()),
+            "fn a() {\n\n    y;\n}\nfn b() {}\n"
+        );
+        assert_eq!(layout.create_text([].into_iter()), "fn a() {\n\n\n}\nfn b() {}\n");
+
+        let (layout, blocks_number) = BlockLayout::new(&chars, &units, 0);
+        assert_eq!(blocks_number, 2);
+        assert_eq!(layout.create_text([1].into_iter()), "fn b() {}\n");
+    }
+}
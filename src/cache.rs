@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::{fs, process};
+
+use once_cell::sync::{Lazy, OnceCell};
+
+use crate::fingerprint::CRASH_FINGERPRINT;
+use crate::settings::{Settings, EXTENSION};
+
+const DISK_CACHE_FILE_NAME: &str = "minimizer_cache.txt";
+
+static CACHE: Lazy<Mutex<HashMap<ContentHash, bool>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static DISK_CACHE: OnceCell<DiskCache> = OnceCell::new();
+pub static CACHE_HITS: AtomicU32 = AtomicU32::new(0);
+pub static CACHE_LOOKUPS: AtomicU32 = AtomicU32::new(0);
+
+// Length is part of the key, to make collisions of hashes even less likely
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq)]
+pub struct ContentHash {
+    pub(crate) len: usize,
+    pub(crate) hash: u64,
+}
+
+// FNV-1a is used instead of DefaultHasher, because its result must not change between runs and rust versions
+pub fn hash_content(content: &[u8]) -> ContentHash {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let hash = content.iter().fold(FNV_OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+    });
+    ContentHash {
+        len: content.len(),
+        hash,
+    }
+}
+
+pub fn get_cached_result(content_hash: ContentHash) -> Option<bool> {
+    CACHE_LOOKUPS.fetch_add(1, Ordering::Relaxed);
+    let result = CACHE.lock().expect("Cache lock poisoned").get(&content_hash).copied();
+    if result.is_some() {
+        CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+    }
+    result
+}
+
+pub fn save_result_to_cache(content_hash: ContentHash, is_broken: bool) {
+    CACHE
+        .lock()
+        .expect("Cache lock poisoned")
+        .insert(content_hash, is_broken);
+
+    if let Some(disk_cache) = DISK_CACHE.get() {
+        let line = format_disk_cache_line(disk_cache.settings_key, content_hash, is_broken);
+        let mut file = disk_cache.file.lock().expect("Disk cache lock poisoned");
+        if let Err(e) = writeln!(file, "{line}") {
+            eprintln!("Error writing to cache file, reason {e}");
+            process::exit(1);
+        }
+    }
+}
+
+// Results saved on disk are shared between runs, so they can be used only when command and criteria are the same
+struct DiskCache {
+    settings_key: u64,
+    file: Mutex<File>,
+}
+
+pub fn load_disk_cache(settings: &Settings) {
+    let Some(cache_dir) = &settings.cache_dir else {
+        return;
+    };
+    if let Err(e) = fs::create_dir_all(cache_dir) {
+        eprintln!("Error creating cache directory {cache_dir}, reason {e}");
+        process::exit(1);
+    }
+    let cache_file = Path::new(cache_dir).join(DISK_CACHE_FILE_NAME);
+    let settings_key = hash_content(create_settings_description(settings).as_bytes()).hash;
+
+    let content = match fs::read_to_string(&cache_file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => {
+            eprintln!("Error reading cache file {}, reason {}", cache_file.display(), e);
+            process::exit(1);
+        }
+    };
+    let loaded_results = content
+        .lines()
+        .filter_map(parse_disk_cache_line)
+        .filter(|(line_settings_key, _, _)| *line_settings_key == settings_key)
+        .map(|(_, content_hash, is_broken)| (content_hash, is_broken))
+        .collect::<Vec<_>>();
+    let loaded_number = loaded_results.len();
+    CACHE.lock().expect("Cache lock poisoned").extend(loaded_results);
+
+    let file = match OpenOptions::new().create(true).append(true).open(&cache_file) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Error opening cache file {}, reason {}", cache_file.display(), e);
+            process::exit(1);
+        }
+    };
+    DISK_CACHE
+        .set(DiskCache {
+            settings_key,
+            file: Mutex::new(file),
+        })
+        .unwrap_or_else(|_| panic!("Disk cache loaded twice, which should not happen"));
+
+    if settings.is_verbose_message_visible() {
+        println!("Loaded {loaded_number} cached results from {}", cache_file.display());
+    }
+}
+
+// Everything that may change result of command for the same content, must be included here
+// Crash fingerprint is known only after checking initial file, so cache must be loaded after that
+fn create_settings_description(settings: &Settings) -> String {
+    format!(
+        "{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}|{:?}",
+        settings.command,
+        settings
+            .interestingness_script
+            .as_ref()
+            .map(|script| (script, fs::read(script).map(|content| hash_content(&content)).ok())),
+        settings.exec,
+        settings.command_a,
+        settings.command_b,
+        settings.compare_in,
+        settings
+            .normalize_regex
+            .iter()
+            .map(regex::Regex::as_str)
+            .collect::<Vec<_>>(),
+        settings.stdin,
+        settings.additional_command,
+        settings.additional_expect_exit_code,
+        settings.additional_reject_exit_code,
+        settings.additional_ignored_info,
+        settings.file_symbol,
+        EXTENSION.get(),
+        // Interestingness script sees file with the same name as input file
+        settings.get_input_file_name(),
+        settings.broken_info,
+        settings.ignored_info,
+        settings
+            .broken_regex
+            .iter()
+            .map(regex::Regex::as_str)
+            .collect::<Vec<_>>(),
+        settings
+            .ignored_regex
+            .iter()
+            .map(regex::Regex::as_str)
+            .collect::<Vec<_>>(),
+        settings.broken_in,
+        settings.ignored_in,
+        settings.expect_exit_code,
+        settings.expect_signal,
+        settings.reject_exit_code,
+        settings.command_timeout,
+        settings.timeout_is_broken,
+        settings.memory_limit,
+        settings.cpu_time_limit,
+        settings.max_output_bytes,
+        settings.max_file_size,
+        settings.limit_is_broken,
+        settings.slower_than,
+        settings.memory_above,
+        settings.timing_runs,
+        settings.disable_file_name_escaping,
+        settings.same_crash,
+        settings.same_crash_frames,
+        settings.retries,
+        settings.get_required_broken_runs(),
+        CRASH_FINGERPRINT.get(),
+    )
+}
+
+fn format_disk_cache_line(settings_key: u64, content_hash: ContentHash, is_broken: bool) -> String {
+    format!(
+        "{settings_key:016x} {} {:016x} {}",
+        content_hash.len,
+        content_hash.hash,
+        u8::from(is_broken)
+    )
+}
+
+fn parse_disk_cache_line(line: &str) -> Option<(u64, ContentHash, bool)> {
+    let mut parts = line.split(' ');
+    let settings_key = u64::from_str_radix(parts.next()?, 16).ok()?;
+    let len = parts.next()?.parse().ok()?;
+    let hash = u64::from_str_radix(parts.next()?, 16).ok()?;
+    let is_broken = match parts.next()? {
+        "0" => false,
+        "1" => true,
+        _ => return None,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    Some((settings_key, ContentHash { len, hash }, is_broken))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::settings_from;
+
+    #[test]
+    fn test_hash_content() {
+        assert_eq!(hash_content(b""), hash_content(b""));
+        assert_eq!(hash_content(b"").hash, 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash_content(b"a").hash, 0xaf63_dc4c_8601_ec8c);
+        assert_ne!(hash_content(b"ab"), hash_content(b"ba"));
+        assert_eq!(hash_content(b"abc").len, 3);
+    }
+
+    #[test]
+    fn test_cache_results() {
+        let broken = hash_content(b"test_cache_results broken");
+        let not_broken = hash_content(b"test_cache_results not broken");
+        let missing = hash_content(b"test_cache_results missing");
+
+        save_result_to_cache(broken, true);
+        save_result_to_cache(not_broken, false);
+
+        assert_eq!(get_cached_result(broken), Some(true));
+        assert_eq!(get_cached_result(not_broken), Some(false));
+        assert_eq!(get_cached_result(missing), None);
+    }
+
+    #[test]
+    fn test_disk_cache_line() {
+        let content_hash = hash_content(b"content");
+        let line = format_disk_cache_line(0xabc, content_hash, true);
+        assert_eq!(parse_disk_cache_line(&line), Some((0xabc, content_hash, true)));
+
+        let line = format_disk_cache_line(u64::MAX, content_hash, false);
+        assert_eq!(parse_disk_cache_line(&line), Some((u64::MAX, content_hash, false)));
+
+        // Line may be only partially written, when app was killed
+        assert_eq!(parse_disk_cache_line(&line[..line.len() - 2]), None);
+        assert_eq!(parse_disk_cache_line("0000000000000abc 7 zz 1"), None);
+        assert_eq!(parse_disk_cache_line(""), None);
+    }
+
+    #[test]
+    fn test_settings_description() {
+        let settings = settings_from(&["-b", "X"]);
+        assert_eq!(
+            create_settings_description(&settings),
+            create_settings_description(&settings_from(&["-b", "X"]))
+        );
+        assert_ne!(
+            create_settings_description(&settings),
+            create_settings_description(&settings_from(&["-b", "Y"]))
+        );
+
+        let mut other_input = settings_from(&["-b", "X"]);
+        other_input.input_file = "dir/other.txt".to_string();
+        assert_ne!(
+            create_settings_description(&settings),
+            create_settings_description(&other_input)
+        );
+    }
+}
@@ -1,20 +1,61 @@
 use std::fmt::Debug;
 use std::os::unix::prelude::ExitStatusExt;
 use std::path::Path;
-use std::process::{Output, Stdio};
+use std::process::{ExitStatus, Output};
+use std::time::Duration;
 use std::{fs, process};
 
 use crate::data_trait::SaveSliceToFile;
-use crate::settings::{get_temp_file, Settings};
+use crate::fingerprint::matches_crash_fingerprint;
+use crate::interrupt::is_stop_requested;
+use crate::runner::{killed_by_signal, run_command, CommandResult};
+use crate::settings::{get_temp_dir, get_temp_file, OutputScope, Settings};
 
 pub fn create_command(settings: &Settings) -> String {
-    let base_command = create_single_command_str(settings, &get_temp_file(), &settings.command);
-    if let Some(additional_command) = &settings.additional_command {
-        let new_command = create_single_command_str(settings, &get_temp_file(), additional_command);
-        format!("{base_command}; {new_command}")
-    } else {
-        base_command
+    if let Some(interestingness_script) = &settings.interestingness_script {
+        return format!("cd \"{}\" && \"{interestingness_script}\"", get_temp_dir());
+    }
+    if !settings.exec.is_empty() {
+        return format!("{:?}", create_exec_args(settings, &get_temp_file()));
+    }
+    create_single_command_str(settings, &get_temp_file(), settings.get_command())
+}
+
+// Additional command is always run with shell, also in exec mode
+pub fn create_additional_command(settings: &Settings) -> Option<String> {
+    settings
+        .additional_command
+        .as_ref()
+        .map(|additional_command| create_single_command_str(settings, &get_temp_file(), additional_command))
+}
+
+// Used only to show commands to user
+pub fn create_commands_description(settings: &Settings) -> String {
+    let mut description = create_command(settings);
+    if let Some(command_b) = create_second_command(settings) {
+        description.push_str(&format!("\nCompared with command:\n{command_b}"));
+    }
+    if let Some(additional_command) = create_additional_command(settings) {
+        description.push_str(&format!("\nAdditional command:\n{additional_command}"));
     }
+    description
+}
+
+// Second command of differential mode, first one is returned by `create_command`
+pub fn create_second_command(settings: &Settings) -> Option<String> {
+    settings
+        .command_b
+        .as_ref()
+        .map(|command_b| create_single_command_str(settings, &get_temp_file(), command_b))
+}
+
+// In exec mode, arguments are passed directly to program, so escaping is not needed
+fn create_exec_args(settings: &Settings, file_name: &str) -> Vec<String> {
+    settings
+        .exec
+        .iter()
+        .map(|arg| arg.replace(&settings.file_symbol, file_name))
+        .collect()
 }
 
 fn create_single_command_str(settings: &Settings, file_name: &str, input_command: &str) -> String {
@@ -29,54 +70,517 @@ pub fn check_if_is_broken<T>(content: &[T], settings: &Settings) -> (bool, Strin
 where
     T: Clone + SaveSliceToFile + Send + Sync + Debug,
 {
-    if let Err(e) = T::save_slice_to_file(content, &get_temp_file()) {
-        eprintln!("Error writing file {}, reason {}", &get_temp_file(), e);
+    let tested_file = save_tested_file(content, settings);
+    let required_broken_runs = settings.get_required_broken_runs();
+
+    let mut broken_runs = 0;
+    let mut last_output = String::new();
+    let mut broken_output = None;
+    let mut broken_peak_memory = None;
+    for run in 1..=settings.retries {
+        let (is_broken, output, peak_memory_bytes) = check_tested_file(settings, &tested_file);
+        if is_broken {
+            broken_runs += 1;
+            broken_output = Some(output);
+            broken_peak_memory = peak_memory_bytes;
+        } else {
+            last_output = output;
+        }
+        // Result is already known, so there is no need to run command again
+        let remaining_runs = settings.retries - run;
+        if broken_runs >= required_broken_runs
+            || broken_runs + remaining_runs < required_broken_runs
+            || is_stop_requested()
+        {
+            break;
+        }
+    }
+
+    let is_broken = broken_runs >= required_broken_runs;
+    if settings.retries > 1 && settings.is_extra_verbose_message_visible() {
+        println!(
+            "Content was broken in {broken_runs} runs, required {required_broken_runs} of {}",
+            settings.retries
+        );
+    }
+    // Printed only after final verdict, because content may be still rejected by other criteria or retries
+    if let (true, Some(peak_memory_bytes)) = (is_broken, broken_peak_memory) {
+        if settings.is_verbose_message_visible() {
+            println!(
+                "Peak memory usage of accepted content - {}",
+                format_megabytes(peak_memory_bytes)
+            );
+        }
+    }
+    match broken_output {
+        Some(broken_output) if is_broken => (true, broken_output),
+        _ => (false, last_output),
+    }
+}
+
+// Runs command multiple times on the same content, to check how stable is its result
+pub fn count_broken_runs<T>(content: &[T], settings: &Settings, runs: u32) -> u32
+where
+    T: Clone + SaveSliceToFile + Send + Sync + Debug,
+{
+    let tested_file = save_tested_file(content, settings);
+    (0..runs)
+        .take_while(|_| !is_stop_requested())
+        .map(|_| u32::from(check_tested_file(settings, &tested_file).0))
+        .sum()
+}
+
+fn save_tested_file<T>(content: &[T], settings: &Settings) -> String
+where
+    T: Clone + SaveSliceToFile + Send + Sync + Debug,
+{
+    let tested_file = get_tested_file(settings);
+    if let Err(e) = T::save_slice_to_file(content, &tested_file) {
+        eprintln!("Error writing file {}, reason {}", &tested_file, e);
         process::exit(1);
     }
-    let command = create_command(settings);
+    tested_file
+}
+
+// Single run of all commands on already saved file
+// Peak memory of main command is returned only when it is used as criterion
+fn check_tested_file(settings: &Settings, tested_file: &str) -> (bool, String, Option<u64>) {
+    let (is_broken, output, peak_memory_bytes) = check_main_command(settings, tested_file);
+    if !is_broken {
+        if settings.is_extra_verbose_message_visible() {
+            println!("Content rejected by main command");
+        }
+        return (false, output, peak_memory_bytes);
+    }
+    if settings.same_crash && !matches_crash_fingerprint(&output, settings) {
+        if settings.is_extra_verbose_message_visible() {
+            println!("Content rejected, because crash fingerprint is different");
+        }
+        return (false, output, peak_memory_bytes);
+    }
+
+    // Additional command is run only when main command found broken file, because it would not change result otherwise
+    let Some(additional_command) = create_additional_command(settings) else {
+        return (true, output, peak_memory_bytes);
+    };
+    let (is_valid, additional_output) = check_additional_command(settings, tested_file, &additional_command);
+    if !is_valid && settings.is_extra_verbose_message_visible() {
+        println!("Content rejected by additional command");
+    }
+
+    (
+        is_valid,
+        format!("{output}\n====== Additional command\n{additional_output}"),
+        peak_memory_bytes,
+    )
+}
+
+fn check_main_command(settings: &Settings, tested_file: &str) -> (bool, String, Option<u64>) {
+    if let Some(command_b) = create_second_command(settings) {
+        let (is_broken, output) = check_commands_difference(settings, tested_file, &command_b);
+        return (is_broken, output, None);
+    }
+
+    let mut command = create_process_command(settings);
+    attach_stdin(&mut command, tested_file, settings);
 
-    // TODO split into 2 different commands
     let start_time = std::time::Instant::now();
-    let output = process::Command::new("sh")
-        .arg("-c")
-        .arg(&command)
-        .stderr(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .unwrap()
-        .wait_with_output()
-        .unwrap();
+    let result = run_command(command, &settings.get_run_limits());
     let elapsed = start_time.elapsed();
-    let all = collect_output(&output);
+    let output_streams = OutputStreams::new(&result.output);
+    let all = output_streams.all();
 
-    let contains_broken_info = settings.broken_info.iter().any(|info| all.contains(info));
-    let contains_ignored_info = settings
-        .ignored_info
-        .as_ref()
-        .map_or(false, |ignored| ignored.iter().any(|info| all.contains(info)));
+    if let Some((is_broken, output)) =
+        check_exceeded_limits(settings, std::slice::from_ref(&result), false, &all, elapsed)
+    {
+        return (is_broken, output, None);
+    }
+    let CommandResult {
+        output,
+        peak_memory_bytes,
+        ..
+    } = result;
+
+    if settings.interestingness_script.is_some() {
+        let is_broken = output.status.success();
+        if settings.print_command_output && settings.is_normal_message_visible() {
+            println!(
+                "=========================\n{all}\nMinimization result - interestingness script finished with status {:?}, is broken \"{is_broken}\", took {elapsed:?}\n=========================",
+                output.status.code()
+            );
+        }
+        return (is_broken, all, None);
+    }
 
-    let is_broken = contains_broken_info && !contains_ignored_info;
+    let broken_info_stream = contains_broken_info(&output_streams, settings);
+    let ignored_info_stream = contains_ignored_info(&output_streams, settings);
+    let contains_broken_info = !has_broken_info(settings) || broken_info_stream.is_some();
+    let contains_ignored_info = ignored_info_stream.is_some();
+    let matches_status = matches_expected_status(output.status, settings);
+
+    let has_criteria =
+        settings.has_output_criteria() || settings.slower_than.is_some() || settings.memory_above.is_some();
+    let mut is_broken = has_criteria && contains_broken_info && matches_status && !contains_ignored_info;
+
+    let mut memory_info = String::new();
+    if let Some(memory_above) = settings.memory_above {
+        let uses_more_memory = peak_memory_bytes > memory_above * 1024 * 1024;
+        is_broken &= uses_more_memory;
+        memory_info = format!(
+            ", peak memory {}, uses more memory than {memory_above} MB \"{uses_more_memory}\"",
+            format_megabytes(peak_memory_bytes)
+        );
+    }
+
+    // Measuring time requires additional runs, so it is done only when other criteria already matched
+    let mut timing_info = String::new();
+    if let (true, Some(slower_than)) = (is_broken, settings.slower_than) {
+        let median_time = measure_median_time(settings, tested_file, elapsed, slower_than);
+        is_broken = median_time > slower_than;
+        timing_info = format!(", median time {median_time:?}, slower than {slower_than:?} \"{is_broken}\"");
+    }
+
+    if settings.print_command_output && settings.is_normal_message_visible() {
+        println!(
+            "=========================\n{all}\nMinimization result - contains broken info \"{contains_broken_info}\"{}, contains ignored info \"{contains_ignored_info}\"{}, matches expected status \"{matches_status}\"{memory_info}{timing_info}, is broken \"{is_broken}\", took {elapsed:?}\n=========================",
+            format_matched_stream(broken_info_stream),
+            format_matched_stream(ignored_info_stream)
+        );
+    }
+
+    (is_broken, all, settings.memory_above.map(|_| peak_memory_bytes))
+}
+
+// First run was already done, when checking other criteria, so only remaining runs are executed
+// When first run is much faster than threshold, measuring is stopped, because even slower next runs would rarely change verdict
+fn measure_median_time(
+    settings: &Settings,
+    tested_file: &str,
+    first_run_time: Duration,
+    slower_than: Duration,
+) -> Duration {
+    if first_run_time < slower_than / 2 {
+        return first_run_time;
+    }
+    let mut times = vec![first_run_time];
+    for _ in 1..settings.timing_runs {
+        let mut command = create_process_command(settings);
+        attach_stdin(&mut command, tested_file, settings);
+        let start_time = std::time::Instant::now();
+        run_command(command, &settings.get_run_limits());
+        times.push(start_time.elapsed());
+    }
+    times.sort();
+    let median_time = median_duration(&times);
+
+    if settings.is_verbose_message_visible() {
+        println!(
+            "Timing statistics - median {median_time:?}, min {:?}, max {:?} of {} runs, threshold {:?}",
+            times[0],
+            times[times.len() - 1],
+            times.len(),
+            slower_than
+        );
+    }
+    median_time
+}
+
+fn format_megabytes(bytes: u64) -> String {
+    format!("{:.1} MB", bytes as f64 / 1024.0 / 1024.0)
+}
+
+// Durations must be sorted
+fn median_duration(times: &[Duration]) -> Duration {
+    let middle = times.len() / 2;
+    if times.len().is_multiple_of(2) {
+        (times[middle - 1] + times[middle]) / 2
+    } else {
+        times[middle]
+    }
+}
+
+// In differential mode file is broken, when normalized outputs of both commands are different
+fn check_commands_difference(settings: &Settings, tested_file: &str, command_b: &str) -> (bool, String) {
+    let start_time = std::time::Instant::now();
+    let mut results = Vec::new();
+    for command_str in [create_command(settings), command_b.to_string()] {
+        let mut command = create_shell_command(&command_str);
+        attach_stdin(&mut command, tested_file, settings);
+        results.push(run_command(command, &settings.get_run_limits()));
+    }
+    let elapsed = start_time.elapsed();
+    let streams_a = OutputStreams::new(&results[0].output);
+    let streams_b = OutputStreams::new(&results[1].output);
+    let all = format!("{}\n====== Compared command\n{}", streams_a.all(), streams_b.all());
+
+    if let Some(verdict) = check_exceeded_limits(settings, &results, false, &all, elapsed) {
+        return verdict;
+    }
+
+    let outputs_differ = outputs_differ(&streams_a, &streams_b, settings);
+    let contains_ignored_info =
+        contains_ignored_info(&streams_a, settings).is_some() || contains_ignored_info(&streams_b, settings).is_some();
+    let is_broken = outputs_differ && !contains_ignored_info;
 
     if settings.print_command_output && settings.is_normal_message_visible() {
         println!(
-            "=========================\n{all}\nMinimization result - contains broken info \"{contains_broken_info}\", contains ignored info \"{contains_ignored_info}\", is broken \"{is_broken}\", took {elapsed:?}\n========================="
+            "=========================\n{all}\nMinimization result - outputs differ \"{outputs_differ}\", contains ignored info \"{contains_ignored_info}\", is broken \"{is_broken}\", took {elapsed:?}\n========================="
         );
     }
 
     (is_broken, all)
 }
 
-pub fn collect_output(output: &Output) -> String {
-    let stdout = &output.stdout;
-    let stderr = &output.stderr;
-    let stdout_str = String::from_utf8_lossy(stdout);
-    let stderr_str = String::from_utf8_lossy(stderr);
-    let status_signal = format!(
-        "====== Status {:?}, Signal {:?}",
-        output.status.code(),
-        output.status.signal()
+// Output of command killed after exceeding timeout or resource limit is incomplete, so other criteria are not checked
+// Additional command only validates file, so its killed run is never valid
+fn check_exceeded_limits(
+    settings: &Settings,
+    results: &[CommandResult],
+    is_additional_command: bool,
+    all: &str,
+    elapsed: Duration,
+) -> Option<(bool, String)> {
+    let (exceeded, verdict) = if results.iter().any(|result| result.timed_out) {
+        ("timeout".to_string(), settings.timeout_is_broken)
+    } else if let Some(limit_exceeded) = results.iter().find_map(|result| result.limit_exceeded) {
+        (limit_exceeded.to_string(), settings.limit_is_broken)
+    } else {
+        return None;
+    };
+    let (result_name, verdict_name, verdict) = if is_additional_command {
+        ("Additional command result", "is valid", false)
+    } else {
+        ("Minimization result", "is broken", verdict)
+    };
+
+    if settings.print_command_output && settings.is_normal_message_visible() {
+        println!(
+            "=========================\n{all}\n{result_name} - command exceeded {exceeded}, {verdict_name} \"{verdict}\", took {elapsed:?}\n========================="
+        );
+    }
+    Some((verdict, all.to_string()))
+}
+
+fn outputs_differ(streams_a: &OutputStreams, streams_b: &OutputStreams, settings: &Settings) -> bool {
+    let normalize = |output: String| {
+        settings
+            .normalize_regex
+            .iter()
+            .fold(output, |output, regex| regex.replace_all(&output, "").to_string())
+    };
+    normalize(streams_a.get(settings.compare_in)) != normalize(streams_b.get(settings.compare_in))
+}
+
+// Additional command validates file, so file is accepted only when its output does not contain any ignored info and its status is expected
+fn check_additional_command(settings: &Settings, tested_file: &str, additional_command: &str) -> (bool, String) {
+    let mut command = create_shell_command(additional_command);
+    attach_stdin(&mut command, tested_file, settings);
+
+    let start_time = std::time::Instant::now();
+    let result = run_command(command, &settings.get_run_limits());
+    let elapsed = start_time.elapsed();
+    let output = &result.output;
+    let output_streams = OutputStreams::new(output);
+    let all = output_streams.all();
+
+    if let Some(verdict) = check_exceeded_limits(settings, std::slice::from_ref(&result), true, &all, elapsed) {
+        return verdict;
+    }
+
+    let ignored_info_stream = contains_additional_ignored_info(&output_streams, settings);
+    let contains_ignored_info = ignored_info_stream.is_some();
+    let matches_status = matches_status(
+        output.status,
+        &settings.additional_expect_exit_code,
+        &[],
+        &settings.additional_reject_exit_code,
+        true,
     );
-    format!("{stdout_str}\n{stderr_str}\n\n{status_signal}")
+
+    let is_valid = matches_status && !contains_ignored_info;
+
+    if settings.print_command_output && settings.is_normal_message_visible() {
+        println!(
+            "=========================\n{all}\nAdditional command result - contains ignored info \"{contains_ignored_info}\"{}, matches expected status \"{matches_status}\", is valid \"{is_valid}\", took {elapsed:?}\n=========================",
+            format_matched_stream(ignored_info_stream)
+        );
+    }
+
+    (is_valid, all)
+}
+
+fn attach_stdin(command: &mut process::Command, tested_file: &str, settings: &Settings) {
+    if !settings.stdin {
+        return;
+    }
+    match fs::File::open(tested_file) {
+        Ok(file) => {
+            command.stdin(file);
+        }
+        Err(e) => {
+            eprintln!("Error opening file {}, reason {}", tested_file, e);
+            process::exit(1);
+        }
+    }
+}
+
+// Interestingness scripts expect tested file with original name, so it is placed inside separate directory
+fn get_tested_file(settings: &Settings) -> String {
+    if settings.interestingness_script.is_none() {
+        return get_temp_file();
+    }
+    let temp_dir = get_temp_dir();
+    if let Err(e) = fs::create_dir_all(&temp_dir) {
+        eprintln!("Error creating directory {temp_dir}, reason {e}");
+        process::exit(1);
+    }
+    format!("{temp_dir}/{}", settings.get_input_file_name())
+}
+
+fn create_process_command(settings: &Settings) -> process::Command {
+    if let Some(interestingness_script) = &settings.interestingness_script {
+        let mut command = process::Command::new(interestingness_script);
+        command.current_dir(get_temp_dir());
+        command
+    } else if !settings.exec.is_empty() {
+        let exec_args = create_exec_args(settings, &get_temp_file());
+        let mut command = process::Command::new(&exec_args[0]);
+        command.args(&exec_args[1..]);
+        command
+    } else {
+        create_shell_command(&create_command(settings))
+    }
+}
+
+fn create_shell_command(command_str: &str) -> process::Command {
+    let mut command = process::Command::new("sh");
+    command.arg("-c").arg(command_str);
+    command
+}
+
+fn has_broken_info(settings: &Settings) -> bool {
+    !settings.broken_info.is_empty() || !settings.broken_regex.is_empty()
+}
+
+// Returns stream in which broken info was found
+fn contains_broken_info(output_streams: &OutputStreams, settings: &Settings) -> Option<OutputScope> {
+    output_streams.find_matching_stream(settings.broken_in, |output| {
+        settings.broken_info.iter().any(|info| output.contains(info))
+            || settings.broken_regex.iter().any(|regex| regex.is_match(output))
+    })
+}
+
+// Returns stream in which ignored info was found
+fn contains_ignored_info(output_streams: &OutputStreams, settings: &Settings) -> Option<OutputScope> {
+    output_streams.find_matching_stream(settings.ignored_in, |output| {
+        settings
+            .ignored_info
+            .as_ref()
+            .is_some_and(|ignored| ignored.iter().any(|info| output.contains(info)))
+            || settings.ignored_regex.iter().any(|regex| regex.is_match(output))
+    })
+}
+
+// Ignored info of main command is also checked in output of additional command, e.g. `-z SyntaxError` with `python3 -m compileall {}`
+fn contains_additional_ignored_info(output_streams: &OutputStreams, settings: &Settings) -> Option<OutputScope> {
+    contains_ignored_info(output_streams, settings).or_else(|| {
+        output_streams.find_matching_stream(OutputScope::Any, |output| {
+            settings
+                .additional_ignored_info
+                .iter()
+                .any(|info| output.contains(info))
+        })
+    })
+}
+
+fn format_matched_stream(stream: Option<OutputScope>) -> String {
+    stream.map(|stream| format!(" (in {stream})")).unwrap_or_default()
+}
+
+// Checks exit code and signal criteria, when none of them is set, any status is accepted
+fn matches_expected_status(status: ExitStatus, settings: &Settings) -> bool {
+    matches_status(
+        status,
+        &settings.expect_exit_code,
+        &settings.expect_signal,
+        &settings.reject_exit_code,
+        settings.uses_shell(),
+    )
+}
+
+// Shell(e.g. dash) may not pass signal of killed child, but exits with code 128 + signal number, so with shell this code is also treated as signal
+fn matches_status(
+    status: ExitStatus,
+    expect_exit_code: &[i32],
+    expect_signal: &[i32],
+    reject_exit_code: &[i32],
+    through_shell: bool,
+) -> bool {
+    if status.code().is_some_and(|code| reject_exit_code.contains(&code)) {
+        return false;
+    }
+    if expect_exit_code.is_empty() && expect_signal.is_empty() {
+        return true;
+    }
+    status.code().is_some_and(|code| expect_exit_code.contains(&code))
+        || expect_signal.iter().any(|&signal| {
+            if through_shell {
+                killed_by_signal(status, signal)
+            } else {
+                status.signal() == Some(signal)
+            }
+        })
+}
+
+pub struct OutputStreams {
+    pub(crate) stdout: String,
+    pub(crate) stderr: String,
+    pub(crate) status: String,
+}
+
+impl OutputStreams {
+    pub fn new(output: &Output) -> Self {
+        Self {
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            status: format!(
+                "====== Status {:?}, Signal {:?}",
+                output.status.code(),
+                output.status.signal()
+            ),
+        }
+    }
+
+    pub fn get(&self, scope: OutputScope) -> String {
+        match scope {
+            OutputScope::Stdout => self.stdout.clone(),
+            OutputScope::Stderr => self.stderr.clone(),
+            OutputScope::Status => self.status.clone(),
+            OutputScope::Any => self.all(),
+        }
+    }
+
+    pub fn all(&self) -> String {
+        format!("{}\n{}\n\n{}", self.stdout, self.stderr, self.status)
+    }
+
+    // With `Any` scope, streams are checked one by one, to know which of them matched
+    // Only when none of them matched, all streams joined together are checked
+    pub fn find_matching_stream(&self, scope: OutputScope, matches: impl Fn(&str) -> bool) -> Option<OutputScope> {
+        match scope {
+            OutputScope::Stdout => matches(&self.stdout).then_some(OutputScope::Stdout),
+            OutputScope::Stderr => matches(&self.stderr).then_some(OutputScope::Stderr),
+            OutputScope::Status => matches(&self.status).then_some(OutputScope::Status),
+            OutputScope::Any => [
+                (OutputScope::Stdout, &self.stdout),
+                (OutputScope::Stderr, &self.stderr),
+                (OutputScope::Status, &self.status),
+            ]
+            .into_iter()
+            .find_map(|(stream, output)| matches(output).then_some(stream))
+            .or_else(|| matches(&self.all()).then_some(OutputScope::Any)),
+        }
+    }
 }
 
 pub fn load_and_check_files(settings: &Settings) -> Vec<u8> {
@@ -106,3 +610,207 @@ pub fn load_and_check_files(settings: &Settings) -> Vec<u8> {
 
     content
 }
+
+#[cfg(test)]
+mod tests {
+
+    use crate::runner::RunLimits;
+    use crate::settings::{settings_from, try_parse_settings};
+
+    use super::*;
+
+    fn streams(stdout: &str, stderr: &str) -> OutputStreams {
+        OutputStreams {
+            stdout: stdout.to_string(),
+            stderr: stderr.to_string(),
+            status: "====== Status Some(101), Signal None".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_broken_info_mixed_with_regex() {
+        let settings = settings_from(&["-b", "LITERAL", "--broken-regex", r"panicked at .*src/parser\.rs:\d+"]);
+
+        assert!(contains_broken_info(&streams("found LITERAL here", ""), &settings).is_some());
+        assert!(contains_broken_info(
+            &streams("", "thread 'main' panicked at /home/src/parser.rs:21:5"),
+            &settings
+        )
+        .is_some());
+        assert!(contains_broken_info(&streams("thread 'main' panicked at src/parser.rs:xx", ""), &settings).is_none());
+    }
+
+    #[test]
+    fn test_ignored_info_mixed_with_regex() {
+        let settings = settings_from(&["-b", "BROKEN", "-z", "SyntaxError", "--ignored-regex", r"(?m)^Sorry:"]);
+
+        assert!(contains_ignored_info(&streams("File has SyntaxError", ""), &settings).is_some());
+        assert!(contains_ignored_info(&streams("", "Sorry: bad"), &settings).is_some());
+        assert!(contains_ignored_info(&streams("Not Sorry: bad", ""), &settings).is_none());
+
+        let settings = settings_from(&["-b", "BROKEN"]);
+        assert!(contains_ignored_info(&streams("Anything", "Anything"), &settings).is_none());
+    }
+
+    #[test]
+    fn test_broken_info_scopes() {
+        let output_streams = streams("echoed error", "real failure");
+
+        let settings = settings_from(&["-b", "error"]);
+        assert_eq!(
+            contains_broken_info(&output_streams, &settings),
+            Some(OutputScope::Stdout)
+        );
+        let settings = settings_from(&["-b", "error", "--broken-in", "stderr"]);
+        assert_eq!(contains_broken_info(&output_streams, &settings), None);
+        let settings = settings_from(&["-b", "failure", "--broken-in", "stderr"]);
+        assert_eq!(
+            contains_broken_info(&output_streams, &settings),
+            Some(OutputScope::Stderr)
+        );
+        let settings = settings_from(&["-b", "Status Some(101)", "--broken-in", "status"]);
+        assert_eq!(
+            contains_broken_info(&output_streams, &settings),
+            Some(OutputScope::Status)
+        );
+        // Pattern which is split between streams, can be found only in all streams joined together
+        let settings = settings_from(&["--broken-regex", r"error\s+real"]);
+        assert_eq!(contains_broken_info(&output_streams, &settings), Some(OutputScope::Any));
+
+        let settings = settings_from(&["-b", "x", "-z", "error", "--ignored-in", "stderr"]);
+        assert_eq!(contains_ignored_info(&output_streams, &settings), None);
+    }
+
+    #[test]
+    fn test_matches_expected_status() {
+        let exited_with = |code: i32| ExitStatus::from_raw(code << 8);
+        let killed_by = ExitStatus::from_raw;
+
+        let settings = settings_from(&["--expect-exit-code", "101", "--expect-signal", "SIGSEGV"]);
+        assert!(matches_expected_status(exited_with(101), &settings));
+        assert!(matches_expected_status(killed_by(libc::SIGSEGV), &settings));
+        assert!(!matches_expected_status(exited_with(0), &settings));
+        assert!(!matches_expected_status(killed_by(libc::SIGABRT), &settings));
+
+        let settings = settings_from(&["--reject-exit-code", "0"]);
+        assert!(!matches_expected_status(exited_with(0), &settings));
+        assert!(matches_expected_status(exited_with(1), &settings));
+        assert!(matches_expected_status(killed_by(libc::SIGABRT), &settings));
+
+        let settings = settings_from(&["-b", "BROKEN"]);
+        assert!(matches_expected_status(exited_with(0), &settings));
+        assert!(!has_broken_info(&settings_from(&["--expect-exit-code", "1"])));
+    }
+
+    #[test]
+    fn test_expected_signal_through_shell() {
+        // Inner shell is killed, so outer shell exits with code 128 + SIGSEGV instead of being killed itself
+        let result = run_command(
+            create_shell_command("sh -c 'kill -SEGV $$'; exit $?"),
+            &RunLimits::default(),
+        );
+        assert_eq!(result.output.status.code(), Some(128 + libc::SIGSEGV));
+
+        let settings = settings_from(&["--expect-signal", "SIGSEGV"]);
+        assert!(matches_expected_status(result.output.status, &settings));
+        let settings = settings_from(&["--expect-signal", "SIGABRT"]);
+        assert!(!matches_expected_status(result.output.status, &settings));
+
+        // Program run directly e.g. in exec mode, reports real signal, so exit code is not treated as signal
+        assert!(!matches_status(result.output.status, &[], &[libc::SIGSEGV], &[], false));
+    }
+
+    #[test]
+    fn test_additional_command_criteria() {
+        let output_streams = streams("", "SyntaxError: invalid syntax");
+
+        let settings = settings_from(&["-b", "BROKEN", "-k", "python3 -m compileall {}", "-z", "SyntaxError"]);
+        assert_eq!(
+            contains_additional_ignored_info(&output_streams, &settings),
+            Some(OutputScope::Stderr)
+        );
+        let settings = settings_from(&["-b", "BROKEN", "-k", "check {}", "--additional-ignored-info", "invalid"]);
+        assert_eq!(
+            contains_additional_ignored_info(&output_streams, &settings),
+            Some(OutputScope::Stderr)
+        );
+        let settings = settings_from(&["-b", "BROKEN", "-k", "check {}"]);
+        assert_eq!(contains_additional_ignored_info(&output_streams, &settings), None);
+
+        // Expectations of additional command cannot be used without it
+        assert!(try_parse_settings(&["-c", "cat {}", "-b", "X", "--additional-expect-exit-code", "0"]).is_err());
+    }
+
+    #[test]
+    fn test_outputs_differ() {
+        let commands = ["--command-a", "app_a {}", "--command-b", "app_b {}"];
+        let settings_with = |args: &[&str]| try_parse_settings(&[&commands, args].concat()).unwrap();
+
+        let first = streams("result 1 at 0x7ffd1234", "");
+        let second = streams("result 1 at 0x55aa9876", "");
+
+        assert!(outputs_differ(&first, &second, &settings_with(&[])));
+        assert!(!outputs_differ(
+            &first,
+            &second,
+            &settings_with(&["--normalize-regex", "0x[0-9a-f]+"])
+        ));
+        assert!(!outputs_differ(
+            &first,
+            &second,
+            &settings_with(&["--compare-in", "status"])
+        ));
+        assert!(outputs_differ(
+            &first,
+            &streams("result 2 at 0x55aa9876", ""),
+            &settings_with(&["--normalize-regex", "0x[0-9a-f]+"])
+        ));
+
+        // Differential mode cannot be mixed with other commands and broken criteria
+        assert!(try_parse_settings(&[&commands[..], &["-c", "cat {}"]].concat()).is_err());
+        assert!(try_parse_settings(&[&commands[..], &["-b", "X"]].concat()).is_err());
+        assert!(try_parse_settings(&commands[..2]).is_err());
+    }
+
+    #[test]
+    fn test_median_duration() {
+        let millis = |values: &[u64]| {
+            values
+                .iter()
+                .map(|value| Duration::from_millis(*value))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(median_duration(&millis(&[5])), Duration::from_millis(5));
+        assert_eq!(median_duration(&millis(&[1, 2, 100])), Duration::from_millis(2));
+        assert_eq!(median_duration(&millis(&[1, 3, 5, 100])), Duration::from_millis(4));
+    }
+
+    #[test]
+    fn test_exec_args() {
+        let settings =
+            try_parse_settings(&["-b", "X", "--", "./app", "--check", "{}", "\"$HOME\"", "--file={}"]).unwrap();
+        assert_eq!(
+            create_exec_args(&settings, "/tmp/some file.txt"),
+            vec![
+                "./app",
+                "--check",
+                "/tmp/some file.txt",
+                "\"$HOME\"",
+                "--file=/tmp/some file.txt"
+            ]
+        );
+
+        assert!(try_parse_settings(&["-b", "X", "--"]).is_err());
+        assert!(try_parse_settings(&["-b", "X", "-c", "cat {}", "--", "./app"]).is_err());
+    }
+
+    #[test]
+    fn test_no_criteria_is_rejected() {
+        assert!(try_parse_settings(&["-c", "cat {}"]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_regex_is_rejected() {
+        assert!(try_parse_settings(&["-c", "cat {}", "--broken-regex", "(unclosed"]).is_err());
+    }
+}
@@ -1,7 +1,11 @@
+use std::sync::Arc;
 use std::{fs, io};
 
 use strum_macros::Display;
 
+use crate::blocks::BlockLayout;
+use crate::tokens::tokens_to_string;
+
 pub trait DataTraits<T: Clone> {
     fn get_vec(&self) -> &Vec<T>;
     fn replace_vec(&mut self, new_vec: Vec<T>);
@@ -56,31 +60,120 @@ impl DataTraits<char> for MinimizationChars {
     }
 }
 
+pub struct MinimizationTokens {
+    pub(crate) mode: Mode,
+    pub(crate) tokens: Vec<Token>,
+}
+impl DataTraits<Token> for MinimizationTokens {
+    fn get_vec(&self) -> &Vec<Token> {
+        &self.tokens
+    }
+    fn replace_vec(&mut self, new_vec: Vec<Token>) {
+        self.tokens = new_vec;
+    }
+    fn get_mode(&self) -> Mode {
+        self.mode
+    }
+}
+
+// Identifier, number or punctuation character, with whitespaces that follow it
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub(crate) text: String,
+    pub(crate) separator: String,
+}
+
+pub struct MinimizationBlocks {
+    pub(crate) mode: Mode,
+    pub(crate) blocks: Vec<Block>,
+}
+impl DataTraits<Block> for MinimizationBlocks {
+    fn get_vec(&self) -> &Vec<Block> {
+        &self.blocks
+    }
+    fn replace_vec(&mut self, new_vec: Vec<Block>) {
+        self.blocks = new_vec;
+    }
+    fn get_mode(&self) -> Mode {
+        self.mode
+    }
+}
+impl MinimizationBlocks {
+    pub fn new(layout: &Arc<BlockLayout>, blocks_number: usize) -> Self {
+        Self {
+            mode: Mode::Blocks,
+            blocks: (0..blocks_number)
+                .map(|idx| Block {
+                    idx,
+                    layout: Arc::clone(layout),
+                })
+                .collect(),
+        }
+    }
+    pub fn create_text(&self, layout: &BlockLayout) -> String {
+        layout.create_text(self.blocks.iter().map(|block| block.idx))
+    }
+}
+
+// Block that can be removed at current level, text which cannot be removed is kept inside shared layout
+#[derive(Clone, Debug)]
+pub struct Block {
+    pub(crate) idx: usize,
+    pub(crate) layout: Arc<BlockLayout>,
+}
+
 pub trait SaveSliceToFile {
-    fn save_slice_to_file(slice: &[Self], file_name: &str) -> io::Result<()>
+    fn slice_to_bytes(slice: &[Self]) -> Vec<u8>
     where
         Self: Sized;
+
+    fn save_slice_to_file(slice: &[Self], file_name: &str) -> io::Result<()>
+    where
+        Self: Sized,
+    {
+        fs::write(file_name, Self::slice_to_bytes(slice))
+    }
 }
 
 impl SaveSliceToFile for u8 {
+    fn slice_to_bytes(slice: &[u8]) -> Vec<u8> {
+        slice.to_vec()
+    }
     fn save_slice_to_file(slice: &[u8], file_name: &str) -> io::Result<()> {
         fs::write(file_name, slice)
     }
 }
 
 impl SaveSliceToFile for char {
-    fn save_slice_to_file(slice: &[char], file_name: &str) -> io::Result<()> {
-        fs::write(file_name, slice.iter().collect::<String>())
+    fn slice_to_bytes(slice: &[char]) -> Vec<u8> {
+        slice.iter().collect::<String>().into_bytes()
+    }
+}
+
+impl SaveSliceToFile for Block {
+    // Rules never remove all items, so there is always at least one block, which contains layout
+    fn slice_to_bytes(slice: &[Block]) -> Vec<u8> {
+        slice
+            .first()
+            .map(|block| block.layout.create_text(slice.iter().map(|block| block.idx)))
+            .unwrap_or_default()
+            .into_bytes()
+    }
+}
+
+impl SaveSliceToFile for Token {
+    fn slice_to_bytes(slice: &[Token]) -> Vec<u8> {
+        tokens_to_string(slice).into_bytes()
     }
 }
 
 impl SaveSliceToFile for String {
-    fn save_slice_to_file(slice: &[String], file_name: &str) -> io::Result<()> {
-        fs::write(file_name, slice.join("\n"))
+    fn slice_to_bytes(slice: &[String]) -> Vec<u8> {
+        slice.join("\n").into_bytes()
     }
 }
 
-#[derive(Display, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Display, Copy, Clone, Eq, PartialEq)]
 pub enum Mode {
     #[strum(serialize = "bytes")]
     Bytes,
@@ -88,4 +181,8 @@ pub enum Mode {
     Lines,
     #[strum(serialize = "chars")]
     Chars,
+    #[strum(serialize = "blocks")]
+    Blocks,
+    #[strum(serialize = "tokens")]
+    Tokens,
 }
@@ -0,0 +1,124 @@
+use once_cell::sync::{Lazy, OnceCell};
+use regex::Regex;
+
+use crate::settings::Settings;
+
+// Fingerprint of crash found in output of initial file, set only with --same-crash
+pub static CRASH_FINGERPRINT: OnceCell<String> = OnceCell::new();
+
+// `panicked at src/main.rs:2:5:` in new and `panicked at 'message', src/main.rs:2:5` in old versions of Rust
+static PANIC_LOCATION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"panicked at (?:'.*', )?([^\s:']+:\d+)").expect("Invalid panic location regex"));
+// `file.c:12:3: runtime error: signed integer overflow` from UBSan
+static SANITIZER_LOCATION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(\S+:\d+):\d+: runtime error:").expect("Invalid sanitizer location regex"));
+// `    #0 0x4f1e2a in parse_header /src/file.c:12:3` from ASan/UBSan stack trace
+static SANITIZER_FRAME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?m)^\s*#\d+ 0x[0-9a-f]+ in (\S+)").expect("Invalid sanitizer frame regex"));
+// `   3: app::parser::parse` or `   3:     0x55d8c2e1 - app::parser::parse::h1a2b3c4d` from Rust backtrace
+static RUST_FRAME: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"(?m)^\s*\d+:\s+(?:0x[0-9a-f]+ - )?(\S+?)(?:::h[0-9a-f]{16})?$").expect("Invalid rust frame regex")
+});
+
+// Frames of panic handling and sanitizer runtime are the same for all crashes, so they are skipped
+const IGNORED_FRAME_PREFIXES: &[&str] = &[
+    "std::",
+    "core::",
+    "alloc::",
+    "<std::",
+    "<core::",
+    "<alloc::",
+    "rust_begin_unwind",
+    "rust_panic",
+    "__rust",
+    "__asan",
+    "__ubsan",
+    "__sanitizer",
+    "__interceptor",
+    "__libc_start",
+    "_start",
+];
+
+// Creates fingerprint from crash location and top frames of stack trace, returns None when output does not contain any of them
+pub fn extract_fingerprint(output: &str, frames_number: usize) -> Option<String> {
+    let location = PANIC_LOCATION
+        .captures(output)
+        .or_else(|| SANITIZER_LOCATION.captures(output))
+        .map(|captures| captures[1].to_string());
+
+    let mut frames = collect_frames(&SANITIZER_FRAME, output, frames_number);
+    if frames.is_empty() {
+        frames = collect_frames(&RUST_FRAME, output, frames_number);
+    }
+
+    if location.is_none() && frames.is_empty() {
+        return None;
+    }
+    Some(location.into_iter().chain(frames).collect::<Vec<_>>().join(" | "))
+}
+
+fn collect_frames(regex: &Regex, output: &str, frames_number: usize) -> Vec<String> {
+    regex
+        .captures_iter(output)
+        .map(|captures| captures[1].to_string())
+        .filter(|frame| !IGNORED_FRAME_PREFIXES.iter().any(|prefix| frame.starts_with(prefix)))
+        .take(frames_number)
+        .collect()
+}
+
+// Before fingerprint is extracted from initial file, any output is accepted
+pub fn matches_crash_fingerprint(output: &str, settings: &Settings) -> bool {
+    let Some(fingerprint) = CRASH_FINGERPRINT.get() else {
+        return true;
+    };
+    extract_fingerprint(output, settings.same_crash_frames).as_ref() == Some(fingerprint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rust_fingerprint() {
+        let output = "thread 'main' panicked at src/parser.rs:21:5:
+index out of bounds
+stack backtrace:
+   0: rust_begin_unwind
+   1: core::panicking::panic_fmt
+   2: app::parser::parse_item
+   3: app::parser::parse
+   4: app::main
+   5: core::ops::function::FnOnce::call_once";
+        assert_eq!(
+            extract_fingerprint(output, 2),
+            Some("src/parser.rs:21 | app::parser::parse_item | app::parser::parse".to_string())
+        );
+        assert_eq!(
+            extract_fingerprint("panicked at 'explicit panic', src/main.rs:2:5", 3),
+            Some("src/main.rs:2".to_string())
+        );
+        assert_eq!(
+            extract_fingerprint("   7:     0x55d8c2e1 - app::run::h1a2b3c4d5e6f7a8b", 3),
+            Some("app::run".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sanitizer_fingerprint() {
+        let output = "==1==ERROR: AddressSanitizer: heap-buffer-overflow
+    #0 0x4f1e2a in __asan_memcpy
+    #1 0x4f1e2b in parse_header /src/file.c:12:3
+    #2 0x4f1e2c in main /src/main.c:5:1";
+        assert_eq!(extract_fingerprint(output, 3), Some("parse_header | main".to_string()));
+        assert_eq!(
+            extract_fingerprint("file.c:12:3: runtime error: signed integer overflow", 3),
+            Some("file.c:12".to_string())
+        );
+    }
+
+    #[test]
+    fn test_missing_fingerprint() {
+        assert_eq!(extract_fingerprint("", 3), None);
+        assert_eq!(extract_fingerprint("Error: file is invalid", 3), None);
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::HashSet;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+use crate::runner::kill_process_group_by_pid;
+use crate::settings::remove_temp_files;
+
+static STOP_REQUESTED: AtomicBool = AtomicBool::new(false);
+// Groups of commands, which are currently running, so they can be killed without waiting for them to finish
+static RUNNING_PROCESS_GROUPS: Lazy<Mutex<HashSet<u32>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+// First SIGINT/SIGTERM stops minimization, so best result can be verified and saved
+// Second one exits immediately
+pub fn install_interrupt_handler() {
+    let result = ctrlc::set_handler(|| {
+        if STOP_REQUESTED.swap(true, Ordering::SeqCst) {
+            eprintln!("Received second interrupt signal, exiting immediately");
+            kill_running_process_groups();
+            remove_temp_files();
+            process::exit(130);
+        }
+        eprintln!("Received interrupt signal, stopping minimization(press Ctrl-C again to exit immediately)");
+        kill_running_process_groups();
+    });
+    if let Err(e) = result {
+        eprintln!("Error setting interrupt handler, reason {e}");
+        process::exit(1);
+    }
+}
+
+pub fn is_stop_requested() -> bool {
+    STOP_REQUESTED.load(Ordering::SeqCst)
+}
+
+pub fn register_process_group(pid: u32) {
+    RUNNING_PROCESS_GROUPS
+        .lock()
+        .expect("Process groups lock poisoned")
+        .insert(pid);
+}
+
+pub fn unregister_process_group(pid: u32) {
+    RUNNING_PROCESS_GROUPS
+        .lock()
+        .expect("Process groups lock poisoned")
+        .remove(&pid);
+}
+
+fn kill_running_process_groups() {
+    for pid in RUNNING_PROCESS_GROUPS
+        .lock()
+        .expect("Process groups lock poisoned")
+        .iter()
+    {
+        kill_process_group_by_pid(*pid);
+    }
+}
@@ -1,44 +1,84 @@
 use std::fmt::Debug;
 use std::path::Path;
-use std::time::Instant;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 use std::{fs, process};
 
 use clap::Parser;
 use once_cell::sync::Lazy;
-use rand::prelude::ThreadRng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use regex::Regex;
 
-use crate::common::{check_if_is_broken, create_command, load_and_check_files};
-use crate::data_trait::{DataTraits, MinimizationBytes, MinimizationChars, MinimizationLines, Mode, SaveSliceToFile};
-use crate::settings::{Settings, EXTENSION};
-use crate::strategy::common::{Strategies, Strategy};
+use crate::cache::{load_disk_cache, CACHE_HITS, CACHE_LOOKUPS};
+use crate::common::{check_if_is_broken, count_broken_runs, create_commands_description, load_and_check_files};
+use crate::data_trait::{
+    DataTraits, MinimizationBytes, MinimizationChars, MinimizationLines, MinimizationTokens, Mode, SaveSliceToFile,
+};
+use crate::fingerprint::{extract_fingerprint, CRASH_FINGERPRINT};
+use crate::interrupt::{install_interrupt_handler, is_stop_requested};
+use crate::report::{record_pass, save_report};
+use crate::runner::{LIMIT_COUNTER, TIMEOUT_COUNTER};
+use crate::session::{
+    load_session, restore_stats, save_session_checkpoint, start_session, SessionPosition, BINARY_PASSES,
+    RESUMED_ELAPSED, TEXT_PASSES,
+};
+use crate::settings::{remove_temp_files, Settings, EXTENSION};
+use crate::strategy::common::{check_if_exceeded_time, check_if_interrupted, ProcessStatus, Strategies, Strategy};
+use crate::strategy::ddmin::DdminStrategy;
 use crate::strategy::general::GeneralStrategy;
 use crate::strategy::general_multi::GeneralMultiStrategy;
+use crate::strategy::hierarchical::minimize_blocks;
 use crate::strategy::pedantic::PedanticStrategy;
+use crate::tokens::{split_into_tokens, tokens_to_string};
+use crate::trace::start_trace;
 
+mod blocks;
+mod cache;
 mod common;
 mod data_trait;
+mod fingerprint;
+mod interrupt;
+mod report;
 mod rules;
+mod runner;
+mod session;
 mod settings;
 mod strategy;
+mod tokens;
+mod trace;
 
 pub static START_TIME: Lazy<Instant> = Lazy::new(Instant::now);
 
+// With resumed session, time of previous runs is also included
+pub fn get_elapsed_time() -> Duration {
+    START_TIME.elapsed() + RESUMED_ELAPSED.get().copied().unwrap_or_default()
+}
+
 #[derive(Default)]
 pub struct Stats {
     pub(crate) all_iterations: u32,
     pub(crate) current_iteration_count: u32,
     pub(crate) max_attempts: u32,
+    pub(crate) consecutive_cache_hits: u32,
 }
 impl Stats {
     pub fn new() -> Self {
         Stats::default()
     }
     pub fn available(&self) -> u32 {
-        self.max_attempts - self.current_iteration_count
+        self.max_attempts.saturating_sub(self.current_iteration_count)
     }
     pub fn increase(&mut self, how_much: u32) {
         self.all_iterations += how_much;
         self.current_iteration_count += how_much;
+        if how_much > 0 {
+            self.consecutive_cache_hits = 0;
+        }
+    }
+    // Results taken from cache do not use attempts, but when only they are found, there is probably nothing new to test
+    pub fn increase_cache_hits(&mut self, how_much: u32) {
+        self.consecutive_cache_hits += how_much;
     }
     pub fn reset(&mut self) {
         self.current_iteration_count = 0;
@@ -62,56 +102,151 @@ fn main() {
     EXTENSION
         .set(extension_with_dot)
         .expect("Extension set twice, which should not happen");
-    settings.command = settings.command.replace('"', "'");
-
-    let start_time = Instant::now();
-    let initial_file_content = load_and_check_files(&settings);
+    for command in [&mut settings.command, &mut settings.command_a, &mut settings.command_b] {
+        *command = command.take().map(|command| command.replace('"', "'"));
+    }
+    // Script is run inside temporary directory, so relative path would not work
+    if let Some(interestingness_script) = &settings.interestingness_script {
+        match fs::canonicalize(interestingness_script) {
+            Ok(script_path) => settings.interestingness_script = Some(script_path.to_string_lossy().to_string()),
+            Err(e) => {
+                eprintln!("Error reading interestingness script {interestingness_script}, reason {e}");
+                process::exit(1);
+            }
+        }
+    }
 
-    if settings.is_normal_message_visible() {
-        println!(
-            "Starting to test file \"{}\" - Initial file size: {} bytes, with command: \n{}\nList of searched strings: {:?}\nList of ignored strings: {:?}",
-            settings.input_file,
-            initial_file_content.len(),
-            create_command(&settings),
-            settings.broken_info,
-            settings.ignored_info
+    if settings.get_required_broken_runs() > settings.retries {
+        eprintln!(
+            "Required number of broken runs {} cannot be bigger than number of retries {}",
+            settings.get_required_broken_runs(),
+            settings.retries
         );
+        process::exit(1);
     }
 
+    let initial_file_content = load_and_check_files(&settings);
+    let resumed_session = settings.resume.then(|| load_session(&settings, &initial_file_content));
+    let seed = match &resumed_session {
+        Some((state, _)) => state.seed,
+        None => settings.seed.unwrap_or_else(|| rand::thread_rng().gen()),
+    };
+
+    // Tested commands are run in separate process groups, so without handler they would not be stopped by Ctrl-C
+    install_interrupt_handler();
     let mb = MinimizationBytes {
         bytes: initial_file_content.clone(),
         mode: Mode::Bytes,
     };
     let (is_initially_broken, initial_output) = check_if_is_broken(mb.get_vec(), &settings);
+    if is_stop_requested() {
+        exit_interrupted_before_minimization(&settings);
+    }
 
     if !is_initially_broken {
         eprintln!("File is not broken, check command or file");
         eprintln!("==================COMMAND=================");
-        eprintln!("{}", create_command(&settings));
+        eprintln!("{}", create_commands_description(&settings));
         eprintln!("==================OUTPUT==================");
         eprintln!("{initial_output}");
         eprintln!("===========================================");
         process::exit(1);
     }
 
+    if settings.same_crash {
+        match extract_fingerprint(&initial_output, settings.same_crash_frames) {
+            Some(fingerprint) => CRASH_FINGERPRINT
+                .set(fingerprint)
+                .expect("Crash fingerprint set twice, which should not happen"),
+            None => {
+                eprintln!("Could not find crash fingerprint(panic location or stack trace) in output of initial file");
+                eprintln!("==================OUTPUT==================");
+                eprintln!("{initial_output}");
+                eprintln!("===========================================");
+                process::exit(1);
+            }
+        }
+    }
+    load_disk_cache(&settings);
+
+    if settings.is_normal_message_visible() {
+        println!(
+            "Starting to test file \"{}\" - Initial file size: {} bytes, with command{}: \n{}\nList of searched strings: {:?}\nList of ignored strings: {:?}\nList of searched regexes: {:?}\nList of ignored regexes: {:?}\nExpected exit codes: {:?}, expected signals: {:?}, rejected exit codes: {:?}{}\nSeed: {}",
+            settings.input_file,
+            initial_file_content.len(),
+            if settings.stdin { " (file content passed to stdin)" } else { "" },
+            create_commands_description(&settings),
+            settings.broken_info,
+            settings.ignored_info,
+            settings.broken_regex.iter().map(Regex::as_str).collect::<Vec<_>>(),
+            settings.ignored_regex.iter().map(Regex::as_str).collect::<Vec<_>>(),
+            settings.expect_exit_code,
+            settings.expect_signal,
+            settings.reject_exit_code,
+            CRASH_FINGERPRINT
+                .get()
+                .map(|fingerprint| format!("\nCrash fingerprint: {fingerprint}"))
+                .unwrap_or_default(),
+            seed
+        );
+    }
+
+    if settings.retries > 1 && settings.stability_runs > 0 {
+        let broken_runs = count_broken_runs(mb.get_vec(), &settings, settings.stability_runs);
+        if is_stop_requested() {
+            exit_interrupted_before_minimization(&settings);
+        }
+        if settings.is_normal_message_visible() {
+            println!(
+                "Stability check - initial file was broken in {broken_runs} of {} runs ({:.1}% flake rate)",
+                settings.stability_runs,
+                f64::from(settings.stability_runs - broken_runs) / f64::from(settings.stability_runs) * 100.0
+            );
+        }
+    }
+
     if let Err(e) = fs::copy(&settings.input_file, &settings.output_file) {
         eprintln!("Error copying file {}, reason {}", &settings.output_file, e);
         process::exit(1);
     }
 
-    let mut rng = rand::thread_rng();
-    let mut stats = Stats {
-        all_iterations: 0,
-        current_iteration_count: 0,
-        max_attempts: 0,
+    let mut stats = Stats::new();
+    let (start_content, start_position) = match resumed_session {
+        Some((state, content)) => {
+            stats = restore_stats(&state, &settings);
+            RESUMED_ELAPSED
+                .set(state.elapsed)
+                .expect("Resumed elapsed time set twice, which should not happen");
+            if let Err(e) = fs::write(&settings.output_file, &content) {
+                eprintln!("Error writing file {}, reason {}", &settings.output_file, e);
+                process::exit(1);
+            }
+            if settings.is_normal_message_visible() {
+                println!(
+                    "Resuming session from round {}, {} pass, with content of {} bytes, after {} iterations",
+                    state.position.round,
+                    state.position.pass,
+                    content.len(),
+                    state.all_iterations
+                );
+            }
+            (content, Some(state.position))
+        }
+        None => (initial_file_content.clone(), None),
     };
+    start_session(&settings, &initial_file_content, seed);
+    start_trace(&settings, &start_content);
 
-    let mb = minimize_content(initial_file_content.clone(), &mut stats, &settings, &mut rng);
+    let mb = minimize_content(start_content, &mut stats, &settings, seed, start_position);
+    if is_stop_requested() && settings.is_normal_message_visible() {
+        println!("Minimization was interrupted, verifying and saving best result");
+    }
 
-    if !check_if_is_broken(mb.get_vec(), &settings).0 && settings.is_normal_message_visible() {
-        eprintln!("Minimized file was broken at start, but now is not - this may be bug in minimizer or app have not stable output.");
+    let is_finally_broken = check_if_is_broken(mb.get_vec(), &settings).0;
+    if !is_finally_broken && settings.is_normal_message_visible() {
+        eprintln!("Minimized file was broken at start, but now is not - this may be bug in minimizer or app have not stable output(in this case --retries may help).");
         eprintln!("==================COMMAND=================");
-        eprintln!("{}", create_command(&settings));
+        eprintln!("{}", create_commands_description(&settings));
         eprintln!("==================OUTPUT==================");
         eprintln!("{initial_output}");
         eprintln!("==================CONTENT=================");
@@ -139,13 +274,13 @@ fn main() {
                         stats.all_iterations,
                         settings.attempts,
                         settings.reset_attempts,
-                        start_time.elapsed()
+                        get_elapsed_time()
                     );
                 } else {
                     let initial_size_percent = (bytes as f64 / initial_file_content.len() as f64) * 100.0;
                     println!(
                         "File {} was minimized from {} to {} bytes({:.1}% of initial size), after {} iterations (limit was {}, retrying - {}) in {:?}",
-                        &settings.output_file, initial_file_content.len(), bytes, initial_size_percent, stats.all_iterations, settings.attempts, settings.reset_attempts, start_time.elapsed()
+                        &settings.output_file, initial_file_content.len(), bytes, initial_size_percent, stats.all_iterations, settings.attempts, settings.reset_attempts, get_elapsed_time()
                     );
                 }
             }
@@ -155,48 +290,221 @@ fn main() {
             process::exit(1);
         }
     }
+
+    if settings.is_normal_message_visible() {
+        let cache_lookups = CACHE_LOOKUPS.load(Ordering::Relaxed);
+        let cache_hits = CACHE_HITS.load(Ordering::Relaxed);
+        if cache_lookups > 0 {
+            println!(
+                "Results of {cache_hits} of {cache_lookups} tested contents were taken from cache ({:.1}% hit rate)",
+                cache_hits as f64 / cache_lookups as f64 * 100.0
+            );
+        }
+    }
+
+    if settings.has_resource_limits() && settings.is_normal_message_visible() {
+        println!(
+            "Command exceeded resource limits {} times",
+            LIMIT_COUNTER.load(Ordering::Relaxed)
+        );
+    }
+
+    if let Some(command_timeout) = settings.command_timeout {
+        if settings.is_normal_message_visible() {
+            println!(
+                "Command exceeded timeout of {command_timeout} ms {} times",
+                TIMEOUT_COUNTER.load(Ordering::Relaxed)
+            );
+        }
+    }
+
+    save_report(
+        &settings,
+        &stats,
+        seed,
+        initial_file_content.len(),
+        bytes,
+        is_finally_broken,
+    );
+
+    remove_temp_files();
+}
+
+// Command could be killed during checking of initial file, so its result is not reliable and minimization cannot be started
+fn exit_interrupted_before_minimization(settings: &Settings) -> ! {
+    if settings.is_normal_message_visible() {
+        println!(
+            "Minimization was interrupted before it started, file {} was not minimized",
+            settings.input_file
+        );
+    }
+    remove_temp_files();
+    process::exit(130);
 }
 
 fn minimize_content(
     initial_file_content: Vec<u8>,
     stats: &mut Stats,
     settings: &Settings,
-    rng: &mut ThreadRng,
+    seed: u64,
+    start_position: Option<SessionPosition>,
 ) -> MinimizationBytes {
-    let mut mb;
-    if let Ok(initial_str_content) = String::from_utf8(initial_file_content.clone()) {
-        let mut ms = MinimizationLines {
-            mode: Mode::Lines,
-            lines: initial_str_content
-                .split('\n')
-                .map(std::string::ToString::to_string)
-                .collect(),
-        };
-        stats.max_attempts = settings.attempts / 3;
-        get_strategy(settings).minimize(stats, settings, &mut ms, rng);
+    let mut content = initial_file_content;
+    let first_round = start_position.map_or(1, |position| position.round);
+    let mut start_position = start_position;
+    // Removing e.g. chars may allow to remove more lines, so with fixpoint whole process is repeated until nothing changes
+    for round in first_round.. {
+        let old_len = start_position.map_or(content.len(), |position| position.round_start_size);
+        let start_pass = start_position.take().map(|position| position.pass);
+        content = minimize_content_round(content, stats, settings, seed, round, old_len, start_pass);
+        if !settings.fixpoint {
+            break;
+        }
+        if settings.is_verbose_message_visible() {
+            println!("Round {round} minimized file from {old_len} to {} bytes", content.len());
+        }
+        if content.len() == old_len
+            || check_if_exceeded_time(settings) == ProcessStatus::Stop
+            || check_if_interrupted() == ProcessStatus::Stop
+        {
+            break;
+        }
+        stats.reset();
+        if get_round_attempts(stats, settings) == 0 {
+            break;
+        }
+    }
+    MinimizationBytes {
+        mode: Mode::Bytes,
+        bytes: content,
+    }
+}
 
-        let mut mc = MinimizationChars {
-            mode: Mode::Chars,
-            chars: ms.lines.join("\n").chars().collect(),
-        };
-        stats.max_attempts = settings.attempts * 2 / 3;
-        get_strategy(settings).minimize(stats, settings, &mut mc, rng);
+// Attempts are shared between rounds, so each round can use only attempts not used by previous ones
+// With reset attempts, counter is reset after each change, so each round can use all attempts
+fn get_round_attempts(stats: &Stats, settings: &Settings) -> u32 {
+    if settings.reset_attempts {
+        settings.attempts
+    } else {
+        settings
+            .attempts
+            .saturating_sub(stats.all_iterations - stats.current_iteration_count)
+    }
+}
 
-        mb = MinimizationBytes {
-            mode: Mode::Bytes,
-            bytes: mc.chars.iter().collect::<String>().as_bytes().to_vec(),
-        };
+// Each pass uses its own rng created from seed, so resumed session uses the same random rules as not interrupted one
+fn minimize_content_round(
+    initial_file_content: Vec<u8>,
+    stats: &mut Stats,
+    settings: &Settings,
+    seed: u64,
+    round: u32,
+    round_start_size: usize,
+    start_pass: Option<Mode>,
+) -> Vec<u8> {
+    let passes: &[Mode] = if String::from_utf8(initial_file_content.clone()).is_ok() {
+        &TEXT_PASSES
     } else {
-        mb = MinimizationBytes {
+        &BINARY_PASSES
+    };
+
+    let start_pass_idx = start_pass
+        .and_then(|start_pass| passes.iter().position(|pass| *pass == start_pass))
+        .unwrap_or(0);
+
+    let attempts = get_round_attempts(stats, settings);
+    let mut content = initial_file_content;
+    for (pass_idx, pass) in passes.iter().enumerate().skip(start_pass_idx) {
+        // Checkpoint would point to pass, which was not even started, so resumed session would skip interrupted pass
+        if check_if_exceeded_time(settings) == ProcessStatus::Stop || check_if_interrupted() == ProcessStatus::Stop {
+            break;
+        }
+        save_session_checkpoint(
+            SessionPosition {
+                round,
+                pass: *pass,
+                round_start_size,
+            },
+            &content,
+            stats,
+        );
+        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(u64::from(round) << 8 | pass_idx as u64));
+        let (size_before, iterations_before) = (content.len(), stats.all_iterations);
+        content = minimize_pass(content, *pass, stats, settings, &mut rng, attempts);
+        record_pass(
+            settings,
+            round,
+            *pass,
+            size_before,
+            content.len(),
+            stats.all_iterations - iterations_before,
+        );
+    }
+    content
+}
+
+fn minimize_pass(
+    content: Vec<u8>,
+    pass: Mode,
+    stats: &mut Stats,
+    settings: &Settings,
+    rng: &mut StdRng,
+    attempts: u32,
+) -> Vec<u8> {
+    if pass == Mode::Bytes {
+        let mut mb = MinimizationBytes {
             mode: Mode::Bytes,
-            bytes: initial_file_content,
+            bytes: content,
         };
+        stats.max_attempts = attempts;
+        minimize_with_strategy(stats, settings, &mut mb, rng);
+        return mb.bytes;
     }
 
-    stats.max_attempts = settings.attempts;
-    get_strategy(settings).minimize(stats, settings, &mut mb, rng);
+    let text = String::from_utf8(content).expect("Text passes are used only with valid utf8 content");
+    let new_text = match pass {
+        Mode::Blocks => minimize_blocks(stats, settings, text, rng, attempts / 6),
+        Mode::Lines => {
+            let mut ms = MinimizationLines {
+                mode: Mode::Lines,
+                lines: text.split('\n').map(std::string::ToString::to_string).collect(),
+            };
+            stats.max_attempts = attempts / 3;
+            minimize_with_strategy(stats, settings, &mut ms, rng);
+            ms.lines.join("\n")
+        }
+        Mode::Tokens => {
+            let mut mt = MinimizationTokens {
+                mode: Mode::Tokens,
+                tokens: split_into_tokens(&text),
+            };
+            stats.max_attempts = attempts / 2;
+            minimize_with_strategy(stats, settings, &mut mt, rng);
+            tokens_to_string(&mt.tokens)
+        }
+        Mode::Chars => {
+            let mut mc = MinimizationChars {
+                mode: Mode::Chars,
+                chars: text.chars().collect(),
+            };
+            stats.max_attempts = attempts * 2 / 3;
+            minimize_with_strategy(stats, settings, &mut mc, rng);
+            mc.chars.iter().collect()
+        }
+        Mode::Bytes => unreachable!(),
+    };
+    new_text.into_bytes()
+}
 
-    mb
+// Cache hits are counted only inside single strategy run, because many cached results in one pass, do not mean that next pass(e.g. with other mode) will not find anything new
+pub fn minimize_with_strategy<T: Clone + 'static + SaveSliceToFile + Send + Sync + Debug>(
+    stats: &mut Stats,
+    settings: &Settings,
+    mm: &mut dyn DataTraits<T>,
+    rng: &mut StdRng,
+) {
+    stats.consecutive_cache_hits = 0;
+    get_strategy(settings).minimize(stats, settings, mm, rng);
 }
 
 pub fn get_strategy<T: Clone + 'static + SaveSliceToFile + Send + Sync + Debug>(
@@ -206,5 +514,38 @@ pub fn get_strategy<T: Clone + 'static + SaveSliceToFile + Send + Sync + Debug>(
         Strategies::General => Box::new(GeneralStrategy::<T>::new()),
         Strategies::Pedantic => Box::new(PedanticStrategy::<T>::new()),
         Strategies::GeneralMulti => Box::new(GeneralMultiStrategy::<T>::new()),
+        Strategies::Ddmin => Box::new(DdminStrategy::<T>::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::{get_temp_file, settings_from};
+    use crate::strategy::common::MAX_CONSECUTIVE_CACHE_HITS;
+
+    #[test]
+    fn test_saturated_cache_does_not_stop_next_pass() {
+        let _ = EXTENSION.set(".txt".to_string());
+        let settings = settings_from(&["-b", "NEVER_PRINTED"]);
+        let content = (0..20)
+            .map(|idx| format!("saturated cache line {idx}\n"))
+            .collect::<String>()
+            .into_bytes();
+
+        // Previous pass found only cached results, so it was stopped
+        let mut stats = Stats::new();
+        stats.increase_cache_hits(MAX_CONSECUTIVE_CACHE_HITS);
+        minimize_pass(
+            content,
+            Mode::Lines,
+            &mut stats,
+            &settings,
+            &mut StdRng::seed_from_u64(0),
+            30,
+        );
+        let _ = fs::remove_file(get_temp_file());
+
+        assert!(stats.all_iterations > 0);
     }
 }
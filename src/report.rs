@@ -0,0 +1,251 @@
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::{fs, process};
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+
+use crate::cache::{CACHE_HITS, CACHE_LOOKUPS};
+use crate::common::{create_additional_command, create_command, create_second_command};
+use crate::data_trait::Mode;
+use crate::fingerprint::CRASH_FINGERPRINT;
+use crate::interrupt::is_stop_requested;
+use crate::rules::Rule;
+use crate::runner::{COMMAND_RUNS, COMMAND_TIME_MICROS, LIMIT_COUNTER, TIMEOUT_COUNTER};
+use crate::settings::Settings;
+use crate::{get_elapsed_time, Stats};
+
+// Passes and accepted rules are collected only when report is requested, because list of rules may be long
+static REPORT_DATA: Lazy<Mutex<ReportData>> = Lazy::new(|| Mutex::new(ReportData::default()));
+
+#[derive(Default)]
+struct ReportData {
+    passes: Vec<PassReport>,
+    accepted_rules: Vec<AcceptedRuleReport>,
+}
+
+#[derive(Serialize)]
+struct Report {
+    version: &'static str,
+    input_file: String,
+    output_file: String,
+    initial_size: usize,
+    final_size: usize,
+    seed: u64,
+    strategy: String,
+    iterations: u32,
+    elapsed_ms: u64,
+    interrupted: bool,
+    // Whether minimized file was still broken, when it was checked at the end
+    final_verification: bool,
+    commands: CommandsReport,
+    criteria: CriteriaReport,
+    statistics: StatisticsReport,
+    passes: Vec<PassReport>,
+    accepted_rules: Vec<AcceptedRuleReport>,
+}
+
+#[derive(Serialize)]
+struct CommandsReport {
+    command: String,
+    second_command: Option<String>,
+    additional_command: Option<String>,
+    stdin: bool,
+}
+
+#[derive(Serialize)]
+struct CriteriaReport {
+    broken_info: Vec<String>,
+    ignored_info: Vec<String>,
+    broken_regex: Vec<String>,
+    ignored_regex: Vec<String>,
+    broken_in: String,
+    ignored_in: String,
+    expect_exit_code: Vec<i32>,
+    expect_signal: Vec<i32>,
+    reject_exit_code: Vec<i32>,
+    additional_expect_exit_code: Vec<i32>,
+    additional_reject_exit_code: Vec<i32>,
+    additional_ignored_info: Vec<String>,
+    compare_in: Option<String>,
+    normalize_regex: Vec<String>,
+    command_timeout_ms: Option<u64>,
+    timeout_is_broken: bool,
+    limit_is_broken: bool,
+    slower_than_ms: Option<u64>,
+    memory_above_mb: Option<u64>,
+    crash_fingerprint: Option<String>,
+    retries: u32,
+    required_broken_runs: u32,
+}
+
+#[derive(Serialize)]
+struct StatisticsReport {
+    command_runs: u32,
+    total_command_time_ms: u64,
+    average_command_time_ms: f64,
+    cache_lookups: u32,
+    cache_hits: u32,
+    timeouts: u32,
+    resource_limits_exceeded: u32,
+}
+
+#[derive(Serialize)]
+struct PassReport {
+    round: u32,
+    mode: String,
+    size_before: usize,
+    size_after: usize,
+    attempts_used: u32,
+}
+
+#[derive(Serialize)]
+struct AcceptedRuleReport {
+    iteration: u32,
+    mode: String,
+    rule: String,
+    // Length is counted in units of mode e.g. lines or blocks
+    length_before: usize,
+    length_after: usize,
+    size_after: usize,
+}
+
+pub fn record_pass(
+    settings: &Settings,
+    round: u32,
+    mode: Mode,
+    size_before: usize,
+    size_after: usize,
+    attempts_used: u32,
+) {
+    if settings.report_json.is_none() {
+        return;
+    }
+    REPORT_DATA
+        .lock()
+        .expect("Report lock poisoned")
+        .passes
+        .push(PassReport {
+            round,
+            mode: mode.to_string(),
+            size_before,
+            size_after,
+            attempts_used,
+        });
+}
+
+pub fn record_accepted_rule(
+    settings: &Settings,
+    stats: &Stats,
+    rule: &Rule,
+    mode: Mode,
+    length_before: usize,
+    length_after: usize,
+    size_after: usize,
+) {
+    if settings.report_json.is_none() {
+        return;
+    }
+    REPORT_DATA
+        .lock()
+        .expect("Report lock poisoned")
+        .accepted_rules
+        .push(AcceptedRuleReport {
+            iteration: stats.all_iterations,
+            mode: mode.to_string(),
+            rule: rule.to_string(),
+            length_before,
+            length_after,
+            size_after,
+        });
+}
+
+pub fn save_report(
+    settings: &Settings,
+    stats: &Stats,
+    seed: u64,
+    initial_size: usize,
+    final_size: usize,
+    final_verification: bool,
+) {
+    let Some(report_file) = &settings.report_json else {
+        return;
+    };
+    let report_data = std::mem::take(&mut *REPORT_DATA.lock().expect("Report lock poisoned"));
+    let report = Report {
+        version: env!("CARGO_PKG_VERSION"),
+        input_file: settings.input_file.clone(),
+        output_file: settings.output_file.clone(),
+        initial_size,
+        final_size,
+        seed,
+        strategy: format!("{:?}", settings.strategy),
+        iterations: stats.all_iterations,
+        elapsed_ms: get_elapsed_time().as_millis() as u64,
+        interrupted: is_stop_requested(),
+        final_verification,
+        commands: CommandsReport {
+            command: create_command(settings),
+            second_command: create_second_command(settings),
+            additional_command: create_additional_command(settings),
+            stdin: settings.stdin,
+        },
+        criteria: create_criteria_report(settings),
+        statistics: create_statistics_report(),
+        passes: report_data.passes,
+        accepted_rules: report_data.accepted_rules,
+    };
+
+    let content = serde_json::to_string_pretty(&report).expect("Report always can be serialized");
+    if let Err(e) = fs::write(report_file, content) {
+        eprintln!("Error writing report file {report_file}, reason {e}");
+        process::exit(1);
+    }
+}
+
+fn create_criteria_report(settings: &Settings) -> CriteriaReport {
+    let regexes_to_strings = |regexes: &[Regex]| regexes.iter().map(|regex| regex.to_string()).collect();
+    CriteriaReport {
+        broken_info: settings.broken_info.clone(),
+        ignored_info: settings.ignored_info.clone().unwrap_or_default(),
+        broken_regex: regexes_to_strings(&settings.broken_regex),
+        ignored_regex: regexes_to_strings(&settings.ignored_regex),
+        broken_in: settings.broken_in.to_string(),
+        ignored_in: settings.ignored_in.to_string(),
+        expect_exit_code: settings.expect_exit_code.clone(),
+        expect_signal: settings.expect_signal.clone(),
+        reject_exit_code: settings.reject_exit_code.clone(),
+        additional_expect_exit_code: settings.additional_expect_exit_code.clone(),
+        additional_reject_exit_code: settings.additional_reject_exit_code.clone(),
+        additional_ignored_info: settings.additional_ignored_info.clone(),
+        compare_in: settings.is_differential_mode().then(|| settings.compare_in.to_string()),
+        normalize_regex: regexes_to_strings(&settings.normalize_regex),
+        command_timeout_ms: settings.command_timeout,
+        timeout_is_broken: settings.timeout_is_broken,
+        limit_is_broken: settings.limit_is_broken,
+        slower_than_ms: settings.slower_than.map(|duration| duration.as_millis() as u64),
+        memory_above_mb: settings.memory_above,
+        crash_fingerprint: CRASH_FINGERPRINT.get().cloned(),
+        retries: settings.retries,
+        required_broken_runs: settings.get_required_broken_runs(),
+    }
+}
+
+fn create_statistics_report() -> StatisticsReport {
+    let command_runs = COMMAND_RUNS.load(Ordering::Relaxed);
+    let total_command_time_ms = COMMAND_TIME_MICROS.load(Ordering::Relaxed) as f64 / 1000.0;
+    StatisticsReport {
+        command_runs,
+        total_command_time_ms: total_command_time_ms as u64,
+        average_command_time_ms: if command_runs > 0 {
+            total_command_time_ms / f64::from(command_runs)
+        } else {
+            0.0
+        },
+        cache_lookups: CACHE_LOOKUPS.load(Ordering::Relaxed),
+        cache_hits: CACHE_HITS.load(Ordering::Relaxed),
+        timeouts: TIMEOUT_COUNTER.load(Ordering::Relaxed),
+        resource_limits_exceeded: LIMIT_COUNTER.load(Ordering::Relaxed),
+    }
+}
@@ -1,11 +1,13 @@
 use std::fmt::{Debug, Display, Formatter};
 
 use rand::distributions::{Distribution, WeightedIndex};
-use rand::{thread_rng, Rng};
+use rand::Rng;
 use strum_macros::EnumIter;
 
+use crate::cache::{get_cached_result, hash_content, save_result_to_cache};
 use crate::common::check_if_is_broken;
 use crate::data_trait::{Mode, SaveSliceToFile};
+use crate::interrupt::is_stop_requested;
 use crate::settings::Settings;
 use crate::Stats;
 
@@ -20,13 +22,20 @@ pub enum RuleType {
 impl RuleType {
     // Function will panic if not provided weights
     // This is responsibility of caller to provide correct weights
-    pub fn get_random_type(weights: &[(RuleType, usize)]) -> RuleType {
+    pub fn get_random_type<R: Rng>(weights: &[(RuleType, usize)], rng: &mut R) -> RuleType {
         let dist = WeightedIndex::new(weights.iter().map(|(_, weight)| *weight)).expect("Not provided weights");
 
-        weights[dist.sample(&mut thread_rng())].0
+        weights[dist.sample(rng)].0
     }
 }
 
+pub struct RuleResult<T> {
+    // Content after executing rule, only when it is still broken
+    pub(crate) new_content: Option<Vec<T>>,
+    // Result was taken from cache, so command was not executed and attempt was not used
+    pub(crate) from_cache: bool,
+}
+
 #[derive(Clone, Debug)]
 pub enum Rule {
     RemoveContinuous {
@@ -36,6 +45,10 @@ pub enum Rule {
     RemoveRandom {
         indexes_to_remove: Vec<usize>,
     },
+    KeepContinuous {
+        start_idx_included: usize,
+        end_idx_excluded: usize,
+    },
 }
 
 impl Display for Rule {
@@ -48,12 +61,21 @@ impl Display for Rule {
             Rule::RemoveRandom { indexes_to_remove } => {
                 write!(f, "RemoveRandom: {indexes_to_remove:?}")
             }
+            Rule::KeepContinuous {
+                start_idx_included,
+                end_idx_excluded,
+            } => write!(f, "KeepContinuous: {start_idx_included}..{end_idx_excluded}"),
         }
     }
 }
 
 impl Rule {
-    pub fn create_start_end_rule(content_size: usize, number_of_checks: usize, remove_from_start: bool) -> Vec<Rule> {
+    pub fn create_start_end_rule<R: Rng>(
+        content_size: usize,
+        number_of_checks: usize,
+        remove_from_start: bool,
+        rng: &mut R,
+    ) -> Vec<Rule> {
         assert!(content_size >= 5);
 
         let mut chosen_indexes = if content_size < number_of_checks {
@@ -63,7 +85,7 @@ impl Rule {
         } else {
             // When number of requested checks is smaller than content size, we will collect random ranges
             (0..number_of_checks)
-                .map(|_| thread_rng().gen_range(1..content_size))
+                .map(|_| rng.gen_range(1..content_size))
                 .collect::<Vec<_>>()
         };
         chosen_indexes.sort_unstable();
@@ -89,17 +111,17 @@ impl Rule {
         }
     }
 
-    pub fn create_continuous_rule(content_size: usize) -> Rule {
+    pub fn create_continuous_rule<R: Rng>(content_size: usize, rng: &mut R) -> Rule {
         assert!(content_size >= 5);
-        let start_idx = thread_rng().gen_range(1..content_size - 2);
-        let end_idx = thread_rng().gen_range(start_idx + 1..content_size - 1);
+        let start_idx = rng.gen_range(1..content_size - 2);
+        let end_idx = rng.gen_range(start_idx + 1..content_size - 1);
         Rule::RemoveContinuous {
             start_idx_included: start_idx,
             end_idx_excluded: end_idx,
         }
     }
 
-    pub fn create_random_rule(content_size: usize, number_of_indexes: Option<usize>) -> Rule {
+    pub fn create_random_rule<R: Rng>(content_size: usize, number_of_indexes: Option<usize>, rng: &mut R) -> Rule {
         assert!(content_size >= 5);
         if let Some(number_of_indexes) = number_of_indexes {
             assert!(number_of_indexes >= 2);
@@ -107,9 +129,9 @@ impl Rule {
         let max_number_of_indexes =
             number_of_indexes.unwrap_or_else(|| ((content_size as f32).sqrt() as usize).clamp(3, 100));
 
-        let number_of_indexes = thread_rng().gen_range(2..max_number_of_indexes);
+        let number_of_indexes = rng.gen_range(2..max_number_of_indexes);
         let indexes_list = (1..(number_of_indexes.min(content_size - 1)))
-            .map(|_| thread_rng().gen_range(0..content_size))
+            .map(|_| rng.gen_range(0..content_size))
             .collect();
         Rule::RemoveRandom {
             indexes_to_remove: indexes_list,
@@ -153,7 +175,7 @@ impl Rule {
             indexes_to_remove: idxs,
         }
     }
-    pub fn execute<T>(&self, stats: &Stats, content: &[T], mode: Mode, settings: &Settings) -> Option<Vec<T>>
+    pub fn execute<T>(&self, stats: &Stats, content: &[T], mode: Mode, settings: &Settings) -> RuleResult<T>
     where
         T: Clone + SaveSliceToFile + Send + Sync + Debug,
     {
@@ -184,20 +206,44 @@ impl Rule {
                     .collect();
                 test_content = new_vec;
             }
+            Rule::KeepContinuous {
+                start_idx_included,
+                end_idx_excluded,
+            } => {
+                test_content = test_content[*start_idx_included..*end_idx_excluded].to_vec();
+            }
         }
 
-        let (is_broken, _output) = check_if_is_broken(&test_content, settings);
+        let content_hash = hash_content(&T::slice_to_bytes(&test_content));
+        let (is_broken, from_cache) = match get_cached_result(content_hash) {
+            Some(is_broken) => (is_broken, true),
+            None => {
+                let (is_broken, _output) = check_if_is_broken(&test_content, settings);
+                // Command could be killed after interrupt, so its result is not reliable and content must not be accepted
+                // Killed command may e.g. match expected signal or not expected exit code
+                if is_stop_requested() {
+                    return RuleResult {
+                        new_content: None,
+                        from_cache: false,
+                    };
+                }
+                save_result_to_cache(content_hash, is_broken);
+                (is_broken, false)
+            }
+        };
 
-        if is_broken {
-            Some(test_content)
-        } else {
-            None
+        RuleResult {
+            new_content: is_broken.then_some(test_content),
+            from_cache,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use rand::rngs::StdRng;
+    use rand::{thread_rng, SeedableRng};
+
     use super::*;
 
     #[test]
@@ -206,7 +252,7 @@ mod tests {
         let mut remove_from_start = 0;
         let mut remove_from_end = 0;
         for _ in 0..100 {
-            match RuleType::get_random_type(&weights) {
+            match RuleType::get_random_type(&weights, &mut thread_rng()) {
                 RuleType::RemoveFromStart => remove_from_start += 1,
                 RuleType::RemoveFromEnd => remove_from_end += 1,
                 _ => unreachable!(),
@@ -220,7 +266,7 @@ mod tests {
         let content_size = 10;
         let number_of_checks = 100;
         let remove_from_start = true;
-        let rules = Rule::create_start_end_rule(content_size, number_of_checks, remove_from_start);
+        let rules = Rule::create_start_end_rule(content_size, number_of_checks, remove_from_start, &mut thread_rng());
         assert!(!rules.is_empty()); // We can't predict exact number of checks due deduplication, but it should be more than 0
 
         let mut previous_diff = usize::MAX;
@@ -244,7 +290,7 @@ mod tests {
         }
 
         let remove_from_start = false;
-        let rules = Rule::create_start_end_rule(content_size, number_of_checks, remove_from_start);
+        let rules = Rule::create_start_end_rule(content_size, number_of_checks, remove_from_start, &mut thread_rng());
         assert!(!rules.is_empty()); // We can't predict exact number of checks due deduplication, but it should be more than 0
 
         let mut previous_diff = usize::MAX;
@@ -272,7 +318,7 @@ mod tests {
     fn test_rule_create_continuous_rule() {
         for _ in 0..100 {
             let content_size = 10;
-            let rule = Rule::create_continuous_rule(content_size);
+            let rule = Rule::create_continuous_rule(content_size, &mut thread_rng());
             match rule {
                 Rule::RemoveContinuous {
                     start_idx_included,
@@ -291,7 +337,7 @@ mod tests {
     fn test_rule_create_random_rule() {
         for _ in 0..100 {
             let content_size = 10;
-            let rule = Rule::create_random_rule(content_size, None);
+            let rule = Rule::create_random_rule(content_size, None, &mut thread_rng());
             match rule {
                 Rule::RemoveRandom { indexes_to_remove } => {
                     assert!(!indexes_to_remove.is_empty());
@@ -303,4 +349,18 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_rules_with_same_seed_are_equal() {
+        let create_rules = |seed: u64| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut rules = Rule::create_start_end_rule(100, 10, false, &mut rng);
+            rules.push(Rule::create_continuous_rule(100, &mut rng));
+            rules.push(Rule::create_random_rule(100, None, &mut rng));
+            rules.iter().map(ToString::to_string).collect::<Vec<_>>()
+        };
+
+        assert_eq!(create_rules(42), create_rules(42));
+        assert_ne!(create_rules(42), create_rules(43));
+    }
 }
@@ -0,0 +1,389 @@
+use std::io::Read;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use std::{io, process, thread};
+
+use strum_macros::Display;
+
+use crate::interrupt::{register_process_group, unregister_process_group};
+
+pub static TIMEOUT_COUNTER: AtomicU32 = AtomicU32::new(0);
+pub static LIMIT_COUNTER: AtomicU32 = AtomicU32::new(0);
+pub static COMMAND_RUNS: AtomicU32 = AtomicU32::new(0);
+pub static COMMAND_TIME_MICROS: AtomicU64 = AtomicU64::new(0);
+
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(20);
+const READ_CHUNK_SIZE: usize = 8192;
+
+// Messages printed by Rust, C++, Python and sanitizers, when allocation failed
+const OUT_OF_MEMORY_MESSAGES: &[&str] = &[
+    "memory allocation of ",
+    "std::bad_alloc",
+    "out of memory",
+    "Cannot allocate memory",
+    "MemoryError",
+];
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RunLimits {
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) memory_limit_bytes: Option<u64>,
+    pub(crate) cpu_time_limit_seconds: Option<u64>,
+    pub(crate) max_file_size_bytes: Option<u64>,
+    pub(crate) max_output_bytes: Option<usize>,
+}
+
+#[derive(Debug, Display, Clone, Copy, Eq, PartialEq)]
+pub enum ResourceLimit {
+    #[strum(serialize = "memory limit")]
+    Memory,
+    #[strum(serialize = "cpu time limit")]
+    CpuTime,
+    #[strum(serialize = "file size limit")]
+    FileSize,
+    #[strum(serialize = "output size limit")]
+    Output,
+}
+
+pub struct CommandResult {
+    pub(crate) output: Output,
+    pub(crate) timed_out: bool,
+    pub(crate) limit_exceeded: Option<ResourceLimit>,
+    // Max resident memory of command and all its children, which finished before it
+    pub(crate) peak_memory_bytes: u64,
+}
+
+// Runs command in its own process group, so when timeout is exceeded, whole group(e.g. `sh` and its children) can be killed
+// Resource limits are set with setrlimit in child, so they are inherited by all processes started by command
+pub fn run_command(mut command: Command, limits: &RunLimits) -> CommandResult {
+    command.stdout(Stdio::piped()).stderr(Stdio::piped()).process_group(0);
+    let rlimits = collect_rlimits(limits);
+    if !rlimits.is_empty() {
+        // SAFETY: setrlimit is async-signal-safe and closure does not allocate
+        unsafe {
+            command.pre_exec(move || set_rlimits(&rlimits));
+        }
+    }
+    let start_time = Instant::now();
+    // Process is reaped with wait4 instead of Child methods
+    #[allow(clippy::zombie_processes)]
+    let mut child = command.spawn().unwrap_or_else(|e| {
+        eprintln!("Error running command {command:?}, reason {e}");
+        process::exit(1);
+    });
+
+    let pid = child.id();
+    let process_group = ProcessGroupGuard::new(pid);
+    let output_exceeded = Arc::new(AtomicBool::new(false));
+    let stdout_reader = spawn_reader(child.stdout.take(), pid, limits.max_output_bytes, &output_exceeded);
+    let stderr_reader = spawn_reader(child.stderr.take(), pid, limits.max_output_bytes, &output_exceeded);
+
+    let ((status, usage), timed_out) = match limits.timeout {
+        Some(timeout) => wait_with_timeout(&child, timeout),
+        None => (wait(&child), false),
+    };
+    drop(process_group);
+    COMMAND_RUNS.fetch_add(1, Ordering::Relaxed);
+    COMMAND_TIME_MICROS.fetch_add(start_time.elapsed().as_micros() as u64, Ordering::Relaxed);
+    if timed_out {
+        TIMEOUT_COUNTER.fetch_add(1, Ordering::Relaxed);
+    }
+
+    let output = Output {
+        status,
+        stdout: join_reader(stdout_reader),
+        stderr: join_reader(stderr_reader),
+    };
+    let limit_exceeded = if timed_out {
+        None
+    } else {
+        find_exceeded_limit(&output, limits, usage.cpu_time, output_exceeded.load(Ordering::Relaxed))
+    };
+    if limit_exceeded.is_some() {
+        LIMIT_COUNTER.fetch_add(1, Ordering::Relaxed);
+    }
+
+    CommandResult {
+        output,
+        timed_out,
+        limit_exceeded,
+        peak_memory_bytes: usage.peak_memory_bytes,
+    }
+}
+
+// Command is not in foreground process group, so Ctrl-C from terminal does not reach it
+// Guard kills whole group when command finished, minimizer is interrupted or unwinds after panic, so no process is leaked
+// Leftovers started in background may also keep pipes open, so without killing them reading output would never end
+struct ProcessGroupGuard {
+    pid: u32,
+}
+
+impl ProcessGroupGuard {
+    fn new(pid: u32) -> Self {
+        register_process_group(pid);
+        Self { pid }
+    }
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        unregister_process_group(self.pid);
+        kill_process_group_by_pid(self.pid);
+    }
+}
+
+// Type of resource passed to setrlimit is different in glibc and other libc implementations e.g. musl
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+type RlimitResource = libc::__rlimit_resource_t;
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+type RlimitResource = libc::c_int;
+
+fn collect_rlimits(limits: &RunLimits) -> Vec<(RlimitResource, libc::rlimit)> {
+    let mut rlimits = Vec::new();
+    if let Some(memory_limit) = limits.memory_limit_bytes {
+        rlimits.push((libc::RLIMIT_AS, create_rlimit(memory_limit, memory_limit)));
+    }
+    // After soft limit process receives SIGXCPU, which may be ignored, so after one more second it is killed
+    if let Some(cpu_time_limit) = limits.cpu_time_limit_seconds {
+        rlimits.push((libc::RLIMIT_CPU, create_rlimit(cpu_time_limit, cpu_time_limit + 1)));
+    }
+    if let Some(max_file_size) = limits.max_file_size_bytes {
+        rlimits.push((libc::RLIMIT_FSIZE, create_rlimit(max_file_size, max_file_size)));
+    }
+    rlimits
+}
+
+fn create_rlimit(soft: u64, hard: u64) -> libc::rlimit {
+    libc::rlimit {
+        rlim_cur: soft as libc::rlim_t,
+        rlim_max: hard as libc::rlim_t,
+    }
+}
+
+fn set_rlimits(rlimits: &[(RlimitResource, libc::rlimit)]) -> io::Result<()> {
+    for (resource, rlimit) in rlimits {
+        // SAFETY: rlimit is valid pointer to initialized struct
+        if unsafe { libc::setrlimit(*resource, rlimit) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+// Hitting memory limit does not kill process, but allocation fails, so it can be only found in output
+// SIGKILL may be also sent by OOM killer or user, so it is treated as exceeding cpu time limit, only when command used that much cpu time
+fn find_exceeded_limit(
+    output: &Output,
+    limits: &RunLimits,
+    cpu_time: Duration,
+    output_exceeded: bool,
+) -> Option<ResourceLimit> {
+    let status = output.status;
+    if output_exceeded {
+        Some(ResourceLimit::Output)
+    } else if limits.cpu_time_limit_seconds.is_some_and(|cpu_time_limit| {
+        killed_by_signal(status, libc::SIGXCPU)
+            || (killed_by_signal(status, libc::SIGKILL) && cpu_time >= Duration::from_secs(cpu_time_limit))
+    }) {
+        Some(ResourceLimit::CpuTime)
+    } else if limits.max_file_size_bytes.is_some() && killed_by_signal(status, libc::SIGXFSZ) {
+        Some(ResourceLimit::FileSize)
+    } else if limits.memory_limit_bytes.is_some() && contains_out_of_memory_message(&output.stderr) {
+        Some(ResourceLimit::Memory)
+    } else {
+        None
+    }
+}
+
+// When process started by shell is killed, shell exits with code 128 + signal number
+pub(crate) fn killed_by_signal(status: ExitStatus, signal: i32) -> bool {
+    status.signal() == Some(signal) || status.code() == Some(128 + signal)
+}
+
+fn contains_out_of_memory_message(stderr: &[u8]) -> bool {
+    let stderr = String::from_utf8_lossy(stderr);
+    OUT_OF_MEMORY_MESSAGES.iter().any(|message| stderr.contains(message))
+}
+
+struct ResourceUsage {
+    peak_memory_bytes: u64,
+    // User and system time of command and all its children, which finished before it
+    cpu_time: Duration,
+}
+
+fn wait(child: &Child) -> (ExitStatus, ResourceUsage) {
+    wait4(child, 0).expect("Blocking wait always returns status")
+}
+
+// wait4 is used instead of methods of Child, because only it returns resource usage of finished process
+// Returns None, when process is still running and WNOHANG is used
+fn wait4(child: &Child, options: i32) -> Option<(ExitStatus, ResourceUsage)> {
+    let mut status = 0;
+    // SAFETY: rusage is plain struct, for which zeroed memory is valid value
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        // SAFETY: pointers to status and rusage are valid during whole call
+        let result = unsafe { libc::wait4(child.id() as libc::pid_t, &mut status, options, &mut rusage) };
+        if result == 0 {
+            return None;
+        }
+        if result > 0 {
+            let to_duration = |time: libc::timeval| {
+                Duration::from_secs(time.tv_sec as u64) + Duration::from_micros(time.tv_usec as u64)
+            };
+            let usage = ResourceUsage {
+                // On Linux ru_maxrss is in kilobytes
+                peak_memory_bytes: rusage.ru_maxrss as u64 * 1024,
+                cpu_time: to_duration(rusage.ru_utime) + to_duration(rusage.ru_stime),
+            };
+            return Some((ExitStatus::from_raw(status), usage));
+        }
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            eprintln!("Error waiting for command, reason {e}");
+            process::exit(1);
+        }
+    }
+}
+
+fn wait_with_timeout(child: &Child, timeout: Duration) -> ((ExitStatus, ResourceUsage), bool) {
+    let start_time = Instant::now();
+    let mut poll_interval = Duration::from_millis(1);
+    loop {
+        if let Some(exit_info) = wait4(child, libc::WNOHANG) {
+            return (exit_info, false);
+        }
+
+        let elapsed = start_time.elapsed();
+        if elapsed >= timeout {
+            kill_process_group(child);
+            return (wait(child), true);
+        }
+        thread::sleep(poll_interval.min(timeout - elapsed));
+        poll_interval = (poll_interval * 2).min(MAX_POLL_INTERVAL);
+    }
+}
+
+fn kill_process_group(child: &Child) {
+    kill_process_group_by_pid(child.id());
+}
+
+pub(crate) fn kill_process_group_by_pid(pid: u32) {
+    // Child is leader of its own group, so group id is equal to its pid
+    // SAFETY: killpg only sends signal and does not touch memory of this process
+    unsafe {
+        libc::killpg(pid as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+// When output exceeds limit, rest of it is not read and command is killed, because otherwise it could block on full pipe
+fn spawn_reader<R: Read + Send + 'static>(
+    source: Option<R>,
+    pid: u32,
+    max_output_bytes: Option<usize>,
+    output_exceeded: &Arc<AtomicBool>,
+) -> Option<JoinHandle<Vec<u8>>> {
+    let output_exceeded = output_exceeded.clone();
+    source.map(|mut source| {
+        thread::spawn(move || {
+            let Some(max_output_bytes) = max_output_bytes else {
+                let mut buffer = Vec::new();
+                let _ = source.read_to_end(&mut buffer);
+                return buffer;
+            };
+            let mut buffer = Vec::new();
+            let mut chunk = [0; READ_CHUNK_SIZE];
+            while let Ok(read_bytes) = source.read(&mut chunk) {
+                if read_bytes == 0 {
+                    break;
+                }
+                buffer.extend_from_slice(&chunk[..read_bytes]);
+                if buffer.len() > max_output_bytes {
+                    buffer.truncate(max_output_bytes);
+                    output_exceeded.store(true, Ordering::Relaxed);
+                    kill_process_group_by_pid(pid);
+                    break;
+                }
+            }
+            buffer
+        })
+    })
+}
+
+fn join_reader(reader: Option<JoinHandle<Vec<u8>>>) -> Vec<u8> {
+    reader
+        .map(|reader| reader.join().unwrap_or_default())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_shell(command_str: &str, limits: &RunLimits) -> CommandResult {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(command_str);
+        run_command(command, limits)
+    }
+
+    #[test]
+    fn test_output_limit() {
+        let limits = RunLimits {
+            max_output_bytes: Some(1000),
+            ..RunLimits::default()
+        };
+        let result = run_shell("yes", &limits);
+        assert_eq!(result.limit_exceeded, Some(ResourceLimit::Output));
+        assert_eq!(result.output.stdout.len(), 1000);
+
+        let result = run_shell("echo small", &limits);
+        assert_eq!(result.limit_exceeded, None);
+        assert_eq!(result.output.stdout, b"small\n");
+    }
+
+    #[test]
+    fn test_peak_memory() {
+        let result = run_shell("exit 3", &RunLimits::default());
+        assert_eq!(result.output.status.code(), Some(3));
+        assert!(result.peak_memory_bytes > 0);
+
+        let small = run_shell("true", &RunLimits::default()).peak_memory_bytes;
+        let big = run_shell(
+            "dd if=/dev/zero of=/dev/null bs=50M count=1 2>/dev/null",
+            &RunLimits::default(),
+        )
+        .peak_memory_bytes;
+        assert!(big > small + 20_000_000, "{big} should be bigger than {small}");
+    }
+
+    #[test]
+    fn test_file_size_limit() {
+        let limits = RunLimits {
+            max_file_size_bytes: Some(100),
+            ..RunLimits::default()
+        };
+        let file_name = format!("/tmp/minimizer_test_file_size_limit_{}", process::id());
+        let result = run_shell(&format!("head -c 10000 /dev/zero > {file_name}"), &limits);
+        let _ = std::fs::remove_file(&file_name);
+        assert_eq!(result.limit_exceeded, Some(ResourceLimit::FileSize));
+    }
+
+    #[test]
+    fn test_cpu_time_limit() {
+        let limits = RunLimits {
+            cpu_time_limit_seconds: Some(1),
+            ..RunLimits::default()
+        };
+        let result = run_shell("while :; do :; done", &limits);
+        assert_eq!(result.limit_exceeded, Some(ResourceLimit::CpuTime));
+
+        // Killed from outside, before cpu time limit was reached
+        let result = run_shell("kill -9 $$", &limits);
+        assert_eq!(result.output.status.signal(), Some(libc::SIGKILL));
+        assert_eq!(result.limit_exceeded, None);
+    }
+}
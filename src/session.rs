@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use once_cell::sync::OnceCell;
+
+use crate::cache::hash_content;
+use crate::data_trait::Mode;
+use crate::settings::Settings;
+use crate::{get_elapsed_time, Stats};
+
+const STATE_FILE_NAME: &str = "session.txt";
+const CONTENT_FILE_NAME: &str = "content.bin";
+const SESSION_VERSION: u32 = 1;
+
+static SESSION: OnceCell<Session> = OnceCell::new();
+// Time of previous runs of resumed session
+pub static RESUMED_ELAPSED: OnceCell<Duration> = OnceCell::new();
+
+// Order of passes in single round of minimization, for non utf8 files only bytes pass is used
+pub const TEXT_PASSES: [Mode; 5] = [Mode::Blocks, Mode::Lines, Mode::Tokens, Mode::Chars, Mode::Bytes];
+pub const BINARY_PASSES: [Mode; 1] = [Mode::Bytes];
+
+// Place in minimization pipeline, from which minimization is started again after resume
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct SessionPosition {
+    pub(crate) round: u32,
+    pub(crate) pass: Mode,
+    pub(crate) round_start_size: usize,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SessionState {
+    pub(crate) input_hash: u64,
+    pub(crate) seed: u64,
+    pub(crate) position: SessionPosition,
+    pub(crate) all_iterations: u32,
+    pub(crate) current_iteration_count: u32,
+    pub(crate) elapsed: Duration,
+}
+
+struct Session {
+    dir: PathBuf,
+    input_hash: u64,
+    seed: u64,
+    position: Mutex<Option<SessionPosition>>,
+}
+
+// Content of session is saved at start of each pass and after each found smaller content, so it is never far behind
+pub fn start_session(settings: &Settings, input_content: &[u8], seed: u64) {
+    let Some(session_dir) = &settings.session else {
+        return;
+    };
+    if let Err(e) = fs::create_dir_all(session_dir) {
+        eprintln!("Error creating session directory {session_dir}, reason {e}");
+        process::exit(1);
+    }
+    SESSION
+        .set(Session {
+            dir: PathBuf::from(session_dir),
+            input_hash: hash_content(input_content).hash,
+            seed,
+            position: Mutex::new(None),
+        })
+        .unwrap_or_else(|_| panic!("Session started twice, which should not happen"));
+}
+
+// Returns saved state and content, from which minimization should be continued
+pub fn load_session(settings: &Settings, input_content: &[u8]) -> (SessionState, Vec<u8>) {
+    let session_dir = Path::new(settings.session.as_deref().expect("Resume requires session"));
+    let state_file = session_dir.join(STATE_FILE_NAME);
+    let content_file = session_dir.join(CONTENT_FILE_NAME);
+
+    let state = match fs::read_to_string(&state_file) {
+        Ok(state) => parse_state(&state).unwrap_or_else(|| {
+            eprintln!("Session file {} is not valid", state_file.display());
+            process::exit(1);
+        }),
+        Err(e) => {
+            eprintln!("Error reading session file {}, reason {}", state_file.display(), e);
+            process::exit(1);
+        }
+    };
+    if state.input_hash != hash_content(input_content).hash {
+        eprintln!(
+            "Session in {} was created for different input file, so it cannot be resumed",
+            session_dir.display()
+        );
+        process::exit(1);
+    }
+    let content = fs::read(&content_file).unwrap_or_else(|e| {
+        eprintln!("Error reading session content {}, reason {}", content_file.display(), e);
+        process::exit(1);
+    });
+
+    (state, content)
+}
+
+// Resumed run may use smaller number of attempts, so already used attempts cannot be bigger than it
+pub fn restore_stats(state: &SessionState, settings: &Settings) -> Stats {
+    let mut stats = Stats::new();
+    stats.all_iterations = state.all_iterations;
+    stats.current_iteration_count = state.current_iteration_count.min(settings.attempts);
+    stats
+}
+
+pub fn save_session_checkpoint(position: SessionPosition, content: &[u8], stats: &Stats) {
+    let Some(session) = SESSION.get() else {
+        return;
+    };
+    *session.position.lock().expect("Session lock poisoned") = Some(position);
+    save_session(session, position, content, stats);
+}
+
+// Called after finding smaller content, position is the same as in last checkpoint
+pub fn save_session_content(content: &[u8], stats: &Stats) {
+    let Some(session) = SESSION.get() else {
+        return;
+    };
+    let Some(position) = *session.position.lock().expect("Session lock poisoned") else {
+        return;
+    };
+    save_session(session, position, content, stats);
+}
+
+fn save_session(session: &Session, position: SessionPosition, content: &[u8], stats: &Stats) {
+    let state = SessionState {
+        input_hash: session.input_hash,
+        seed: session.seed,
+        position,
+        all_iterations: stats.all_iterations,
+        current_iteration_count: stats.current_iteration_count,
+        elapsed: get_elapsed_time(),
+    };
+    // Content is saved before state, so state never points to content from older pass
+    write_atomically(&session.dir.join(CONTENT_FILE_NAME), content);
+    write_atomically(&session.dir.join(STATE_FILE_NAME), format_state(&state).as_bytes());
+}
+
+// App may be killed at any moment, so file is replaced only when it is fully written
+fn write_atomically(file: &Path, content: &[u8]) {
+    let temp_file = file.with_extension("tmp");
+    if let Err(e) = fs::write(&temp_file, content).and_then(|()| fs::rename(&temp_file, file)) {
+        eprintln!("Error writing session file {}, reason {}", file.display(), e);
+        process::exit(1);
+    }
+}
+
+fn format_state(state: &SessionState) -> String {
+    format!(
+        "version={SESSION_VERSION}\ninput_hash={:016x}\nseed={}\nround={}\npass={}\nround_start_size={}\nall_iterations={}\ncurrent_iteration_count={}\nelapsed_ms={}\n",
+        state.input_hash,
+        state.seed,
+        state.position.round,
+        state.position.pass,
+        state.position.round_start_size,
+        state.all_iterations,
+        state.current_iteration_count,
+        state.elapsed.as_millis()
+    )
+}
+
+fn parse_state(content: &str) -> Option<SessionState> {
+    let values = content
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .collect::<HashMap<_, _>>();
+    if values.get("version")?.parse::<u32>().ok()? != SESSION_VERSION {
+        return None;
+    }
+    Some(SessionState {
+        input_hash: u64::from_str_radix(values.get("input_hash")?, 16).ok()?,
+        seed: values.get("seed")?.parse().ok()?,
+        position: SessionPosition {
+            round: values.get("round")?.parse().ok()?,
+            pass: parse_pass(values.get("pass")?)?,
+            round_start_size: values.get("round_start_size")?.parse().ok()?,
+        },
+        all_iterations: values.get("all_iterations")?.parse().ok()?,
+        current_iteration_count: values.get("current_iteration_count")?.parse().ok()?,
+        elapsed: Duration::from_millis(values.get("elapsed_ms")?.parse().ok()?),
+    })
+}
+
+fn parse_pass(input: &str) -> Option<Mode> {
+    TEXT_PASSES.into_iter().find(|mode| mode.to_string() == input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::settings_from;
+
+    #[test]
+    fn test_resume_with_smaller_budget() {
+        let mut state = SessionState {
+            input_hash: 0,
+            seed: 0,
+            position: SessionPosition {
+                round: 1,
+                pass: Mode::Bytes,
+                round_start_size: 100,
+            },
+            all_iterations: 800,
+            current_iteration_count: 500,
+            elapsed: Duration::ZERO,
+        };
+        let settings = settings_from(&["-b", "X"]);
+
+        let mut stats = restore_stats(&state, &settings);
+        stats.max_attempts = settings.attempts;
+        assert_eq!(stats.all_iterations, 800);
+        assert_eq!(stats.current_iteration_count, 100);
+        assert_eq!(stats.available(), 0);
+
+        state.current_iteration_count = 40;
+        let mut stats = restore_stats(&state, &settings);
+        stats.max_attempts = settings.attempts;
+        assert_eq!(stats.available(), 60);
+    }
+
+    #[test]
+    fn test_state_format() {
+        let state = SessionState {
+            input_hash: 0xabcd,
+            seed: 42,
+            position: SessionPosition {
+                round: 3,
+                pass: Mode::Tokens,
+                round_start_size: 1000,
+            },
+            all_iterations: 500,
+            current_iteration_count: 120,
+            elapsed: Duration::from_millis(123_456),
+        };
+        assert_eq!(parse_state(&format_state(&state)), Some(state.clone()));
+
+        for pass in TEXT_PASSES {
+            assert_eq!(parse_pass(&pass.to_string()), Some(pass));
+        }
+
+        // Partially written or older state is not used
+        let formatted = format_state(&state);
+        assert_eq!(parse_state(&formatted[..formatted.len() / 2]), None);
+        assert_eq!(parse_state(&formatted.replace("version=1", "version=0")), None);
+    }
+}
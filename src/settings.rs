@@ -1,6 +1,12 @@
-use clap::Parser;
+use std::path::Path;
+use std::time::Duration;
+
+use clap::{ArgGroup, Parser};
 use once_cell::sync::OnceCell;
+use regex::Regex;
+use strum_macros::Display;
 
+use crate::runner::RunLimits;
 use crate::strategy::common::Strategies;
 
 pub static EXTENSION: OnceCell<String> = OnceCell::new();
@@ -9,10 +15,37 @@ thread_local! {
     pub static TEMP_FILE: String = format!("/tmp/minimizer_{}_{:?}{}", std::process::id(), std::thread::current().id(), EXTENSION.get().expect("Extension not set, but should be set"));
 }
 
+thread_local! {
+    pub static TEMP_DIR: String = format!("/tmp/minimizer_{}_{:?}_dir", std::process::id(), std::thread::current().id());
+}
+
 pub fn get_temp_file() -> String {
     TEMP_FILE.with(std::clone::Clone::clone)
 }
 
+pub fn get_temp_dir() -> String {
+    TEMP_DIR.with(std::clone::Clone::clone)
+}
+
+// Removes temporary files and directories of all threads of this process
+pub fn remove_temp_files() {
+    let prefix = format!("minimizer_{}_", std::process::id());
+    let Ok(entries) = std::fs::read_dir("/tmp") else {
+        return;
+    };
+    for entry in entries.flatten() {
+        if !entry.file_name().to_string_lossy().starts_with(&prefix) {
+            continue;
+        }
+        let path = entry.path();
+        let _ = if path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        };
+    }
+}
+
 #[derive(Parser)]
 #[command(name = "minimizer")]
 #[command(author = "Rafał Mikrut")]
@@ -21,6 +54,17 @@ pub fn get_temp_file() -> String {
     about = "Minimize files",
     long_about = "App that minimizes files, to find the smallest possible file that have certain output."
 )]
+#[command(group(
+    ArgGroup::new("broken_criteria")
+        .required(true)
+        .multiple(true)
+        .args(["broken_info", "broken_regex", "expect_exit_code", "expect_signal", "reject_exit_code", "timeout_is_broken", "limit_is_broken", "slower_than", "memory_above", "interestingness_script", "command_a"])
+))]
+#[command(group(
+    ArgGroup::new("resource_limits")
+        .multiple(true)
+        .args(["memory_limit", "cpu_time_limit", "max_output_bytes", "max_file_size"])
+))]
 pub struct Settings {
     #[arg(short, long, value_name = "INPUT", help = "Input file that will be minimized")]
     pub(crate) input_file: String,
@@ -42,11 +86,71 @@ pub struct Settings {
 
     #[arg(
         short,
+        long,
+        required_unless_present_any = ["interestingness_script", "exec", "command_a"],
+        value_name = "COMMAND",
+        help = "Command which will be used to minimize e.g. 'godot {} -c 1000'\nBy default {} is used as placeholder for file, but this can be changed.\nCommand is run with `sh -c` and all occurrences of \" will be replaced with ', to avoid this use exec mode"
+    )]
+    pub(crate) command: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SCRIPT",
+        conflicts_with_all = ["command", "additional_command", "broken_info", "broken_regex", "ignored_info", "ignored_regex", "expect_exit_code", "expect_signal", "reject_exit_code"],
+        help = "Interestingness script compatible with C-Reduce/cvise, used instead of command and broken info\nScript is run inside directory, which contains tested file with the same name as input file\nFile is broken when script exits with code 0"
+    )]
+    pub(crate) interestingness_script: Option<String>,
+
+    #[arg(
+        last = true,
+        value_name = "PROGRAM_AND_ARGS",
+        conflicts_with_all = ["command", "interestingness_script", "command_a"],
+        help = "Exec mode - program with its arguments, used instead of command e.g. `-- ./app --check {}`\nProgram is run directly without shell, so quotes, $ and backticks are passed unchanged\nFile symbol is replaced in each argument without any escaping"
+    )]
+    pub(crate) exec: Vec<String>,
+
+    #[arg(
         long,
         value_name = "COMMAND",
-        help = "Command which will be used to minimize e.g. 'godot {} -c 1000'\nBy default {} is used as placeholder for file, but this can be changed.\nAll occurrences of \" will be replaced with '"
+        requires = "command_b",
+        conflicts_with_all = ["command", "interestingness_script", "broken_info", "broken_regex", "expect_exit_code", "expect_signal", "reject_exit_code"],
+        help = "Differential mode - first of two commands, which are run on the same file e.g. two versions of the same app\nFile is broken when outputs of both commands are different"
     )]
-    pub(crate) command: String,
+    pub(crate) command_a: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        requires = "command_a",
+        help = "Differential mode - second of two commands, which output is compared with output of first command"
+    )]
+    pub(crate) command_b: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "STREAM",
+        default_value = "any",
+        requires = "command_a",
+        value_parser = parse_output_scope,
+        help = "Output of commands, which is compared in differential mode(stdout, stderr, status or any)"
+    )]
+    pub(crate) compare_in: OutputScope,
+
+    #[arg(
+        long,
+        value_name = "NORMALIZE_REGEX",
+        requires = "command_a",
+        value_parser = parse_regex,
+        help = "Regex, which matches are removed from outputs of commands before comparing them in differential mode e.g. to remove timestamps or addresses\nCan be used multiple times"
+    )]
+    pub(crate) normalize_regex: Vec<Regex>,
+
+    #[arg(
+        long,
+        help = "Passes content of tested file to stdin of command(and additional command), so file symbol is not required in command",
+        default_value_t = false
+    )]
+    pub(crate) stdin: bool,
 
     #[arg(
         short,
@@ -83,6 +187,64 @@ pub struct Settings {
     )]
     pub(crate) ignored_info: Option<Vec<String>>,
 
+    #[arg(
+        long,
+        value_name = "BROKEN_REGEX",
+        value_parser = parse_regex,
+        help = "Regex searched inside output of command, that will show that file is broken\nCan be mixed with --broken-info, file is broken when any of them matches"
+    )]
+    pub(crate) broken_regex: Vec<Regex>,
+
+    #[arg(
+        long,
+        value_name = "IGNORED_REGEX",
+        value_parser = parse_regex,
+        help = "Regex searched inside output of command, that will be ignored\nCan be mixed with --ignored-info"
+    )]
+    pub(crate) ignored_regex: Vec<Regex>,
+
+    #[arg(
+        long,
+        value_name = "STREAM",
+        default_value = "any",
+        value_parser = parse_output_scope,
+        help = "Output of command, where broken info and broken regexes are searched(stdout, stderr, status or any)"
+    )]
+    pub(crate) broken_in: OutputScope,
+
+    #[arg(
+        long,
+        value_name = "STREAM",
+        default_value = "any",
+        value_parser = parse_output_scope,
+        help = "Output of command, where ignored info and ignored regexes are searched(stdout, stderr, status or any)"
+    )]
+    pub(crate) ignored_in: OutputScope,
+
+    #[arg(
+        long,
+        value_name = "EXIT_CODE",
+        allow_negative_numbers = true,
+        help = "Exit code of command, that will show that file is broken\nCan be combined with broken info, then both must match"
+    )]
+    pub(crate) expect_exit_code: Vec<i32>,
+
+    #[arg(
+        long,
+        value_name = "SIGNAL",
+        value_parser = parse_signal,
+        help = "Signal which killed command, that will show that file is broken e.g. SIGSEGV, SEGV or 11\nCan be combined with broken info, then both must match"
+    )]
+    pub(crate) expect_signal: Vec<i32>,
+
+    #[arg(
+        long,
+        value_name = "EXIT_CODE",
+        allow_negative_numbers = true,
+        help = "Exit code of command, that will show that file is not broken e.g. 0"
+    )]
+    pub(crate) reject_exit_code: Vec<i32>,
+
     #[arg(
         short,
         long,
@@ -130,16 +292,218 @@ pub struct Settings {
         short = 'k',
         long,
         value_name = "ADDITIONAL_COMMAND",
-        help = "Runs additional command, e.g. \"ruff {}\" can be command and \"python3 -m compileall {}\" additional command to verify that output file is valid(in any sense of this word)"
+        help = "Runs additional command, e.g. \"ruff {}\" can be command and \"python3 -m compileall {}\" additional command to verify that output file is valid(in any sense of this word)\nAdditional command is run as separate process, only when main command found broken file\nFile is rejected when output of additional command contains ignored info"
     )]
     pub(crate) additional_command: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "EXIT_CODE",
+        requires = "additional_command",
+        allow_negative_numbers = true,
+        help = "Exit code that additional command must return, so file is accepted e.g. 0, can be used multiple times"
+    )]
+    pub(crate) additional_expect_exit_code: Vec<i32>,
+
+    #[arg(
+        long,
+        value_name = "EXIT_CODE",
+        requires = "additional_command",
+        allow_negative_numbers = true,
+        help = "Exit code of additional command, which rejects file, can be used multiple times"
+    )]
+    pub(crate) additional_reject_exit_code: Vec<i32>,
+
+    #[arg(
+        long,
+        value_name = "IGNORED_CONTENT",
+        requires = "additional_command",
+        help = "Content inside output of additional command, that rejects file, can be used multiple times"
+    )]
+    pub(crate) additional_ignored_info: Vec<String>,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["interestingness_script", "command_a"],
+        help = "Accepts only files, which crash in the same place as initial file\nFingerprint of crash(panic location, top frames of Rust backtrace or ASan/UBSan stack trace) is taken from output of initial file",
+        default_value_t = false
+    )]
+    pub(crate) same_crash: bool,
+
+    #[arg(
+        long,
+        value_name = "NUMBER",
+        requires = "same_crash",
+        help = "Number of top stack frames, which are part of crash fingerprint",
+        default_value_t = 3
+    )]
+    pub(crate) same_crash_frames: usize,
+
+    #[arg(
+        long,
+        value_name = "NUMBER",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Number of times each content is tested, useful with apps which have not stable output",
+        default_value_t = 1
+    )]
+    pub(crate) retries: u32,
+
+    #[arg(
+        long,
+        value_name = "NUMBER",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Number of runs(from --retries), which must find broken file, so content is accepted\nBy default majority of runs is required"
+    )]
+    pub(crate) require: Option<u32>,
+
+    #[arg(
+        long,
+        value_name = "NUMBER",
+        help = "Number of runs of initial file, used to check how stable is result of command, when --retries is bigger than 1\n0 disables this check",
+        default_value_t = 10
+    )]
+    pub(crate) stability_runs: u32,
+
+    #[arg(
+        long,
+        value_name = "MILLISECONDS",
+        help = "Max time in milliseconds that single command can take, after that whole process group of command is killed"
+    )]
+    pub(crate) command_timeout: Option<u64>,
+
+    #[arg(
+        long,
+        requires = "command_timeout",
+        conflicts_with = "timeout_is_ok",
+        help = "File is treated as broken when command exceeded timeout, useful to minimize files that cause hangs",
+        default_value_t = false
+    )]
+    pub(crate) timeout_is_broken: bool,
+
+    #[arg(
+        long,
+        requires = "command_timeout",
+        help = "File is treated as not broken when command exceeded timeout, this is default behavior",
+        default_value_t = false
+    )]
+    pub(crate) timeout_is_ok: bool,
+
+    #[arg(
+        long,
+        value_name = "DURATION",
+        value_parser = parse_duration,
+        conflicts_with_all = ["interestingness_script", "command_a"],
+        help = "File is broken when command takes more time than this e.g. 30s, 500ms or 2m, number without unit means seconds\nCan be combined with other criteria, then all must match"
+    )]
+    pub(crate) slower_than: Option<Duration>,
+
+    #[arg(
+        long,
+        value_name = "MEGABYTES",
+        conflicts_with_all = ["interestingness_script", "command_a"],
+        help = "File is broken when peak resident memory of command is bigger than this number of megabytes\nCan be combined with other criteria, then all must match"
+    )]
+    pub(crate) memory_above: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "NUMBER",
+        requires = "slower_than",
+        value_parser = clap::value_parser!(u32).range(1..),
+        help = "Number of runs used to measure time of command, median of them is compared with --slower-than\nAdditional runs are skipped, when first run takes less than half of --slower-than",
+        default_value_t = 1
+    )]
+    pub(crate) timing_runs: u32,
+
+    #[arg(
+        long,
+        value_name = "MEGABYTES",
+        help = "Max virtual memory in megabytes, that command can use\nWhen it is exceeded, allocations fail, which is found by searching typical out of memory messages in stderr"
+    )]
+    pub(crate) memory_limit: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "Max cpu time in seconds, that command can use, after that command is killed"
+    )]
+    pub(crate) cpu_time_limit: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "BYTES",
+        help = "Max size of stdout and stderr of command, after exceeding it, rest of output is not read and command is killed"
+    )]
+    pub(crate) max_output_bytes: Option<usize>,
+
+    #[arg(long, value_name = "BYTES", help = "Max size of files, that command can create")]
+    pub(crate) max_file_size: Option<u64>,
+
+    #[arg(
+        long,
+        requires = "resource_limits",
+        help = "File is treated as broken when command exceeded any of resource limits, by default such file is treated as not broken",
+        default_value_t = false
+    )]
+    pub(crate) limit_is_broken: bool,
+
+    #[arg(
+        long,
+        value_name = "CACHE_DIR",
+        help = "Directory where results of tested contents are saved, so they can be reused by next runs with the same command and criteria"
+    )]
+    pub(crate) cache_dir: Option<String>,
+
+    #[arg(
+        long,
+        help = "Repeats whole minimization(blocks, lines, tokens, chars and bytes) until file stops getting smaller\nAttempts are shared between rounds, so next round uses only attempts left by previous ones",
+        default_value_t = false
+    )]
+    pub(crate) fixpoint: bool,
+
+    #[arg(
+        long,
+        value_name = "SESSION_DIR",
+        help = "Directory where current state of minimization is saved, so it can be continued with --resume after interruption"
+    )]
+    pub(crate) session: Option<String>,
+
+    #[arg(
+        long,
+        requires = "session",
+        conflicts_with = "seed",
+        help = "Continues minimization saved in session directory, instead of starting it from the beginning\nInput file and other options should be the same as in interrupted run",
+        default_value_t = false
+    )]
+    pub(crate) resume: bool,
+
+    #[arg(
+        long,
+        value_name = "SEED",
+        help = "Seed used to initialize random number generator, so minimization with deterministic command can be repeated\nBy default random seed is used, which is printed at start"
+    )]
+    pub(crate) seed: Option<u64>,
+
+    #[arg(
+        long,
+        value_name = "REPORT_FILE",
+        help = "Saves report of minimization in JSON format to file e.g. sizes and used attempts of each pass, accepted rules and command statistics"
+    )]
+    pub(crate) report_json: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "TRACE_DIR",
+        help = "Directory where each accepted intermediate content is saved as numbered file\nLog with rule, mode and size of each step is saved to trace.log, so minimization can be replayed or bisected"
+    )]
+    pub(crate) trace_dir: Option<String>,
+
     #[clap(
         short,
         long,
         default_value = "general",
         value_parser = parse_strategy,
-        help = "Strategy used to minimize files(General or Pedantic)",
+        help = "Strategy used to minimize files(General, Pedantic, General_Multi or Ddmin)",
     )]
     pub strategy: Strategies,
 }
@@ -149,11 +513,136 @@ fn parse_strategy(input: &str) -> Result<Strategies, String> {
         "general" => Ok(Strategies::General),
         "pedantic" => Ok(Strategies::Pedantic),
         "general_multi" => Ok(Strategies::GeneralMulti),
+        "ddmin" => Ok(Strategies::Ddmin),
         missing => Err(format!("Unknown strategy: {missing}")),
     }
 }
 
+#[derive(Debug, Display, Clone, Copy, Eq, PartialEq)]
+pub enum OutputScope {
+    #[strum(serialize = "stdout")]
+    Stdout,
+    #[strum(serialize = "stderr")]
+    Stderr,
+    #[strum(serialize = "status")]
+    Status,
+    #[strum(serialize = "any")]
+    Any,
+}
+
+fn parse_output_scope(input: &str) -> Result<OutputScope, String> {
+    match input.to_lowercase().as_str() {
+        "stdout" => Ok(OutputScope::Stdout),
+        "stderr" => Ok(OutputScope::Stderr),
+        "status" => Ok(OutputScope::Status),
+        "any" => Ok(OutputScope::Any),
+        missing => Err(format!("Unknown output stream: {missing}")),
+    }
+}
+
+fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let (number, multiplier) = if let Some(number) = input.strip_suffix("ms") {
+        (number, 0.001)
+    } else if let Some(number) = input.strip_suffix('s') {
+        (number, 1.0)
+    } else if let Some(number) = input.strip_suffix('m') {
+        (number, 60.0)
+    } else {
+        (input, 1.0)
+    };
+    match number.trim().parse::<f64>() {
+        Ok(number) if number >= 0.0 && number.is_finite() => Ok(Duration::from_secs_f64(number * multiplier)),
+        _ => Err(format!("Invalid duration: {input}, expected e.g. 30s, 500ms or 2m")),
+    }
+}
+
+fn parse_regex(input: &str) -> Result<Regex, String> {
+    Regex::new(input).map_err(|e| format!("Invalid regex \"{input}\": {e}"))
+}
+
+const SIGNALS: &[(&str, i32)] = &[
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("ILL", libc::SIGILL),
+    ("TRAP", libc::SIGTRAP),
+    ("ABRT", libc::SIGABRT),
+    ("BUS", libc::SIGBUS),
+    ("FPE", libc::SIGFPE),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("SEGV", libc::SIGSEGV),
+    ("USR2", libc::SIGUSR2),
+    ("PIPE", libc::SIGPIPE),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("XCPU", libc::SIGXCPU),
+    ("XFSZ", libc::SIGXFSZ),
+    ("SYS", libc::SIGSYS),
+];
+
+fn parse_signal(input: &str) -> Result<i32, String> {
+    if let Ok(number) = input.parse::<i32>() {
+        return Ok(number);
+    }
+    let upper = input.to_uppercase();
+    let name = upper.strip_prefix("SIG").unwrap_or(&upper);
+    SIGNALS
+        .iter()
+        .find(|(signal_name, _)| *signal_name == name)
+        .map(|(_, number)| *number)
+        .ok_or_else(|| format!("Unknown signal: {input}"))
+}
+
 impl Settings {
+    pub fn is_differential_mode(&self) -> bool {
+        self.command_a.is_some()
+    }
+    // In differential mode, first command is used as main command
+    pub fn get_command(&self) -> &str {
+        self.command
+            .as_deref()
+            .or(self.command_a.as_deref())
+            .expect("Command is required when interestingness script, exec or differential mode is not used")
+    }
+    pub fn get_input_file_name(&self) -> String {
+        Path::new(&self.input_file)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string()
+    }
+    // Criteria checked on output of finished command, without them only timeouts may be treated as broken
+    pub fn has_output_criteria(&self) -> bool {
+        !self.broken_info.is_empty()
+            || !self.broken_regex.is_empty()
+            || !self.expect_exit_code.is_empty()
+            || !self.expect_signal.is_empty()
+            || !self.reject_exit_code.is_empty()
+    }
+    // Main command is run with `sh -c`, unless exec mode or interestingness script is used
+    pub fn uses_shell(&self) -> bool {
+        self.exec.is_empty() && self.interestingness_script.is_none()
+    }
+    pub fn get_required_broken_runs(&self) -> u32 {
+        self.require.unwrap_or(self.retries / 2 + 1)
+    }
+    pub fn get_run_limits(&self) -> RunLimits {
+        RunLimits {
+            timeout: self.command_timeout.map(Duration::from_millis),
+            memory_limit_bytes: self.memory_limit.map(|memory_limit| memory_limit * 1024 * 1024),
+            cpu_time_limit_seconds: self.cpu_time_limit,
+            max_file_size_bytes: self.max_file_size,
+            max_output_bytes: self.max_output_bytes,
+        }
+    }
+    pub fn has_resource_limits(&self) -> bool {
+        self.memory_limit.is_some()
+            || self.cpu_time_limit.is_some()
+            || self.max_output_bytes.is_some()
+            || self.max_file_size.is_some()
+    }
     pub fn is_normal_message_visible(&self) -> bool {
         !self.quiet
     }
@@ -165,14 +654,63 @@ impl Settings {
     }
 }
 
+// Only required input, output and attempts are set, so command and criteria must be passed in args
+#[cfg(test)]
+pub(crate) fn try_parse_settings(args: &[&str]) -> Result<Settings, clap::Error> {
+    let base = ["minimizer", "-i", "input.txt", "-o", "output.txt", "-a", "100"];
+    Settings::try_parse_from(base.iter().chain(args.iter()))
+}
+
+#[cfg(test)]
+pub(crate) fn settings_from(args: &[&str]) -> Settings {
+    try_parse_settings(&[&["-c", "cat {}"], args].concat()).expect("Invalid arguments")
+}
+
 #[cfg(test)]
 mod tests {
     use clap::CommandFactory;
 
-    use crate::settings::Settings;
+    use std::time::Duration;
+
+    use crate::settings::{parse_duration, parse_signal, settings_from, try_parse_settings, Settings};
 
     #[test]
     fn verify_cli() {
         Settings::command().debug_assert();
     }
+
+    #[test]
+    fn test_parse_signal() {
+        assert_eq!(parse_signal("SIGSEGV"), Ok(libc::SIGSEGV));
+        assert_eq!(parse_signal("segv"), Ok(libc::SIGSEGV));
+        assert_eq!(parse_signal("Abrt"), Ok(libc::SIGABRT));
+        assert_eq!(parse_signal("11"), Ok(11));
+        assert!(parse_signal("SIGNOTHING").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30s"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("2m"), Ok(Duration::from_secs(120)));
+        assert!(parse_duration("fast").is_err());
+        assert!(parse_duration("-1s").is_err());
+        assert!(parse_duration("").is_err());
+    }
+
+    #[test]
+    fn test_required_broken_runs() {
+        let settings_with = |args: &[&str]| settings_from(&[&["-b", "X"], args].concat());
+
+        assert_eq!(settings_with(&[]).get_required_broken_runs(), 1);
+        assert_eq!(settings_with(&["--retries", "3"]).get_required_broken_runs(), 2);
+        assert_eq!(settings_with(&["--retries", "4"]).get_required_broken_runs(), 3);
+        assert_eq!(
+            settings_with(&["--retries", "5", "--require", "1"]).get_required_broken_runs(),
+            1
+        );
+        assert!(try_parse_settings(&["-c", "cat {}", "-b", "X", "--retries", "0"]).is_err());
+    }
 }
@@ -1,4 +1,6 @@
 pub mod common;
+pub mod ddmin;
 pub mod general;
 pub mod general_multi;
+pub mod hierarchical;
 pub mod pedantic;
@@ -1,25 +1,33 @@
 use std::fmt::Debug;
-use std::process;
+use std::{fs, process};
 
-use rand::prelude::ThreadRng;
+use rand::rngs::StdRng;
 
 use crate::data_trait::{DataTraits, Mode, SaveSliceToFile};
+use crate::interrupt::is_stop_requested;
+use crate::report::record_accepted_rule;
 use crate::rules::Rule;
+use crate::session::save_session_content;
 use crate::settings::Settings;
-use crate::{Stats, START_TIME};
+use crate::trace::save_trace_rule_step;
+use crate::{get_elapsed_time, Stats};
+
+// After so many results in a row taken from cache, it is quite sure, that all possible contents were already tested
+pub(crate) const MAX_CONSECUTIVE_CACHE_HITS: u32 = 10_000;
 
 #[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
 pub enum Strategies {
     General,
     Pedantic,
     GeneralMulti,
+    Ddmin,
 }
 
 pub trait Strategy<T>
 where
     T: Clone + SaveSliceToFile + Send + Sync + Debug,
 {
-    fn minimize(&self, stats: &mut Stats, settings: &Settings, mm: &mut dyn DataTraits<T>, rng: &mut ThreadRng);
+    fn minimize(&self, stats: &mut Stats, settings: &Settings, mm: &mut dyn DataTraits<T>, rng: &mut StdRng);
 }
 
 #[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq)]
@@ -27,9 +35,11 @@ pub enum ProcessStatus {
     Continue,
     Stop,
 }
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn extend_results(
     changed: bool,
     iterations: u32,
+    cache_hits: u32,
     old_len: usize,
     new_len: usize,
     stats: &mut Stats,
@@ -37,6 +47,7 @@ pub(crate) fn extend_results(
     settings: &Settings,
 ) {
     stats.increase(iterations);
+    stats.increase_cache_hits(cache_hits);
     if changed {
         assert_ne!(old_len, new_len);
         if settings.is_verbose_message_visible() {
@@ -53,7 +64,7 @@ pub(crate) fn extend_results(
 
 pub(crate) fn check_if_exceeded_time(settings: &Settings) -> ProcessStatus {
     if let Some(max_time) = settings.max_time {
-        if START_TIME.elapsed().as_secs() >= max_time as u64 {
+        if get_elapsed_time().as_secs() >= max_time as u64 {
             if settings.is_normal_message_visible() {
                 println!("Max time exceeded, stopping minimization");
             }
@@ -63,10 +74,20 @@ pub(crate) fn check_if_exceeded_time(settings: &Settings) -> ProcessStatus {
     ProcessStatus::Continue
 }
 
+pub(crate) fn check_if_interrupted() -> ProcessStatus {
+    if is_stop_requested() {
+        return ProcessStatus::Stop;
+    }
+    ProcessStatus::Continue
+}
+
 pub(crate) fn check_if_exceeded_iterations(stats: &Stats) -> ProcessStatus {
     if stats.available() == 0 {
         return ProcessStatus::Stop;
     }
+    if stats.consecutive_cache_hits >= MAX_CONSECUTIVE_CACHE_HITS {
+        return ProcessStatus::Stop;
+    }
     ProcessStatus::Continue
 }
 
@@ -77,7 +98,7 @@ pub(crate) fn check_if_stopping_minimization<T>(
     mm: &[T],
     check_length: bool,
 ) -> ProcessStatus {
-    if check_if_exceeded_time(settings) == ProcessStatus::Stop {
+    if check_if_exceeded_time(settings) == ProcessStatus::Stop || check_if_interrupted() == ProcessStatus::Stop {
         return ProcessStatus::Stop;
     }
 
@@ -124,20 +145,47 @@ where
     T: Clone + SaveSliceToFile + Send + Sync + Debug,
 {
     let old_len = mm.len();
-    let new_mm = rule.execute(stats, mm.get_vec(), mm.get_mode(), settings);
-    let is_broken = new_mm.is_some();
-    if let Some(new_mm) = new_mm {
+    let rule_result = rule.execute(stats, mm.get_vec(), mm.get_mode(), settings);
+    let is_broken = rule_result.new_content.is_some();
+    if let Some(new_mm) = rule_result.new_content {
         mm.replace_vec(new_mm);
     }
-    extend_results(is_broken, 1, old_len, mm.len(), stats, mm.get_mode(), settings);
+    let (iterations, cache_hits) = if rule_result.from_cache { (0, 1) } else { (1, 0) };
+    extend_results(
+        is_broken,
+        iterations,
+        cache_hits,
+        old_len,
+        mm.len(),
+        stats,
+        mm.get_mode(),
+        settings,
+    );
 
-    // Also saves current minimal output to file, to be able to get results even if app will be stopped by user in the middle of minimization
     if is_broken {
-        if let Err(e) = T::save_slice_to_file(mm.get_vec(), &settings.output_file) {
-            eprintln!("Error writing file {}, reason {}", &settings.output_file, e);
-            process::exit(1);
-        }
+        save_accepted_content(rule, mm.get_mode(), old_len, mm.get_vec(), stats, settings);
     }
 
     is_broken
 }
+
+// Also saves current minimal output to file, to be able to get results even if app will be stopped by user in the middle of minimization
+pub(crate) fn save_accepted_content<T>(
+    rule: &Rule,
+    mode: Mode,
+    old_len: usize,
+    content: &[T],
+    stats: &Stats,
+    settings: &Settings,
+) where
+    T: Clone + SaveSliceToFile + Send + Sync + Debug,
+{
+    let bytes = T::slice_to_bytes(content);
+    if let Err(e) = fs::write(&settings.output_file, &bytes) {
+        eprintln!("Error writing file {}, reason {}", &settings.output_file, e);
+        process::exit(1);
+    }
+    save_session_content(&bytes, stats);
+    record_accepted_rule(settings, stats, rule, mode, old_len, content.len(), bytes.len());
+    save_trace_rule_step(rule, mode, old_len, content.len(), &bytes, stats);
+}
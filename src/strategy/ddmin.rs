@@ -0,0 +1,140 @@
+use std::fmt::Debug;
+
+use rand::rngs::StdRng;
+
+use crate::data_trait::{DataTraits, SaveSliceToFile};
+use crate::rules::Rule;
+use crate::settings::Settings;
+use crate::strategy::common::{
+    check_if_stopping_minimization, execute_rule_and_extend_results, ProcessStatus, Strategy,
+};
+use crate::Stats;
+
+// Classic delta debugging(ddmin) strategy, which is fully deterministic
+// How this works:
+// - Splits content into n chunks(at start 2)
+// - Tries to leave only one chunk, if file is still broken, starts again with 2 chunks
+// - Tries to remove one chunk, if file is still broken, decreases number of chunks by 1
+// - If nothing could be removed, doubles number of chunks, until each chunk contains only single element
+// - When finished with single element chunks, result is 1-minimal - removing any single element makes file not broken
+pub struct DdminStrategy<T> {
+    _phantom: std::marker::PhantomData<T>,
+}
+impl<T> DdminStrategy<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            _phantom: std::marker::PhantomData,
+        }
+    }
+}
+impl<T> Strategy<T> for DdminStrategy<T>
+where
+    T: Clone + SaveSliceToFile + Send + Sync + Debug,
+{
+    fn minimize(&self, stats: &mut Stats, settings: &Settings, mm: &mut dyn DataTraits<T>, _rng: &mut StdRng) {
+        let mut chunks_number = 2;
+        while mm.len() >= 2 {
+            match test_chunks(stats, settings, mm, chunks_number) {
+                DdminStep::Reduced(new_chunks_number) => chunks_number = new_chunks_number,
+                DdminStep::NotReduced => {
+                    if chunks_number >= mm.len() {
+                        if settings.is_verbose_message_visible() {
+                            println!(
+                                "Content is 1-minimal, removing any of {} {} makes file not broken",
+                                mm.len(),
+                                mm.get_mode()
+                            );
+                        }
+                        return;
+                    }
+                    chunks_number = (chunks_number * 2).min(mm.len());
+                }
+                DdminStep::Stopped => return,
+            }
+        }
+    }
+}
+
+enum DdminStep {
+    Reduced(usize),
+    NotReduced,
+    Stopped,
+}
+
+fn test_chunks<T>(stats: &mut Stats, settings: &Settings, mm: &mut dyn DataTraits<T>, chunks_number: usize) -> DdminStep
+where
+    T: Clone + SaveSliceToFile + Send + Sync + Debug,
+{
+    let chunks = split_into_chunks(mm.len(), chunks_number);
+
+    // Subsets
+    for &(start_idx_included, end_idx_excluded) in &chunks {
+        if check_if_stopping_minimization(stats, settings, mm.get_vec(), false) == ProcessStatus::Stop {
+            return DdminStep::Stopped;
+        }
+        let rule = Rule::KeepContinuous {
+            start_idx_included,
+            end_idx_excluded,
+        };
+        if execute_rule_and_extend_results(&rule, stats, settings, mm) {
+            return DdminStep::Reduced(2);
+        }
+    }
+
+    // Complements - with 2 chunks, complement of one chunk is the other chunk, which was already tested
+    if chunks_number > 2 {
+        for &(start_idx_included, end_idx_excluded) in &chunks {
+            if check_if_stopping_minimization(stats, settings, mm.get_vec(), false) == ProcessStatus::Stop {
+                return DdminStep::Stopped;
+            }
+            let rule = Rule::RemoveContinuous {
+                start_idx_included,
+                end_idx_excluded,
+            };
+            if execute_rule_and_extend_results(&rule, stats, settings, mm) {
+                return DdminStep::Reduced((chunks_number - 1).max(2));
+            }
+        }
+    }
+
+    DdminStep::NotReduced
+}
+
+// Splits content into continuous chunks with almost equal size, returned as ranges start..end
+fn split_into_chunks(content_size: usize, chunks_number: usize) -> Vec<(usize, usize)> {
+    assert!(chunks_number >= 1 && chunks_number <= content_size);
+    (0..chunks_number)
+        .map(|idx| {
+            (
+                idx * content_size / chunks_number,
+                (idx + 1) * content_size / chunks_number,
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_into_chunks() {
+        assert_eq!(split_into_chunks(10, 2), vec![(0, 5), (5, 10)]);
+        assert_eq!(split_into_chunks(10, 3), vec![(0, 3), (3, 6), (6, 10)]);
+        assert_eq!(split_into_chunks(3, 3), vec![(0, 1), (1, 2), (2, 3)]);
+        assert_eq!(split_into_chunks(1, 1), vec![(0, 1)]);
+
+        for content_size in 1..50 {
+            for chunks_number in 1..=content_size {
+                let chunks = split_into_chunks(content_size, chunks_number);
+                assert_eq!(chunks.len(), chunks_number);
+                assert_eq!(chunks[0].0, 0);
+                assert_eq!(chunks[chunks_number - 1].1, content_size);
+                for window in chunks.windows(2) {
+                    assert_eq!(window[0].1, window[1].0);
+                }
+                assert!(chunks.iter().all(|(start, end)| start < end));
+            }
+        }
+    }
+}
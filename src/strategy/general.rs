@@ -1,6 +1,7 @@
 use std::fmt::Debug;
 
-use rand::prelude::ThreadRng;
+use rand::rngs::StdRng;
+use rand::Rng;
 
 use crate::data_trait::{DataTraits, SaveSliceToFile};
 use crate::rules::{Rule, RuleType};
@@ -30,7 +31,7 @@ impl<T> Strategy<T> for GeneralStrategy<T>
 where
     T: Clone + SaveSliceToFile + Send + Sync + Debug,
 {
-    fn minimize(&self, stats: &mut Stats, settings: &Settings, mm: &mut dyn DataTraits<T>, rng: &mut ThreadRng) {
+    fn minimize(&self, stats: &mut Stats, settings: &Settings, mm: &mut dyn DataTraits<T>, rng: &mut StdRng) {
         minimize_internal(stats, settings, mm, rng);
 
         // After minimization to less than 5 elements, we try to remove all combinations of 2, 3, 4 elements
@@ -42,7 +43,7 @@ where
     }
 }
 
-fn minimize_internal<T>(stats: &mut Stats, settings: &Settings, mm: &mut dyn DataTraits<T>, _rng: &mut ThreadRng)
+fn minimize_internal<T>(stats: &mut Stats, settings: &Settings, mm: &mut dyn DataTraits<T>, rng: &mut StdRng)
 where
     T: Clone + SaveSliceToFile + Send + Sync + Debug,
 {
@@ -54,7 +55,7 @@ where
             return;
         }
 
-        let from_start_end_rules = Rule::create_start_end_rule(mm.len(), iters, from_start);
+        let from_start_end_rules = Rule::create_start_end_rule(mm.len(), iters, from_start, rng);
 
         if execute_rules_until_first_found_broken(from_start_end_rules, stats, settings, mm, true)
             == ProcessStatus::Stop
@@ -68,23 +69,23 @@ where
             return;
         }
 
-        let _ = execute_rule_and_extend_results(&get_random_rule(mm.len()), stats, settings, mm);
+        let _ = execute_rule_and_extend_results(&get_random_rule(mm.len(), rng), stats, settings, mm);
     }
 }
 
-pub fn get_random_rule(content_size: usize) -> Rule {
+pub fn get_random_rule<R: Rng>(content_size: usize, rng: &mut R) -> Rule {
     let rules_weights = [
         (RuleType::RemoveFromStart, 2),
         (RuleType::RemoveFromEnd, 10),
         (RuleType::RemoveContinuousFromMiddle, 30),
         (RuleType::RemoveRandom, 10),
     ];
-    let chosen = RuleType::get_random_type(&rules_weights);
+    let chosen = RuleType::get_random_type(&rules_weights, rng);
 
     match chosen {
-        RuleType::RemoveFromStart => Rule::create_start_end_rule(content_size, 1, true)[0].clone(),
-        RuleType::RemoveFromEnd => Rule::create_start_end_rule(content_size, 1, false)[0].clone(),
-        RuleType::RemoveContinuousFromMiddle => Rule::create_continuous_rule(content_size),
-        RuleType::RemoveRandom => Rule::create_random_rule(content_size, None),
+        RuleType::RemoveFromStart => Rule::create_start_end_rule(content_size, 1, true, rng)[0].clone(),
+        RuleType::RemoveFromEnd => Rule::create_start_end_rule(content_size, 1, false, rng)[0].clone(),
+        RuleType::RemoveContinuousFromMiddle => Rule::create_continuous_rule(content_size, rng),
+        RuleType::RemoveRandom => Rule::create_random_rule(content_size, None, rng),
     }
 }
@@ -4,13 +4,16 @@ use std::sync::atomic::AtomicBool;
 use std::thread::available_parallelism;
 
 use once_cell::sync::Lazy;
-use rand::prelude::ThreadRng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use rayon::prelude::*;
 
 use crate::data_trait::{DataTraits, SaveSliceToFile};
 use crate::rules::{Rule, RuleType};
 use crate::settings::Settings;
-use crate::strategy::common::{check_if_stopping_minimization, extend_results, ProcessStatus, Strategy};
+use crate::strategy::common::{
+    check_if_stopping_minimization, extend_results, save_accepted_content, ProcessStatus, Strategy,
+};
 use crate::Stats;
 
 pub static NUMBER_OF_THREADS: Lazy<usize> =
@@ -35,7 +38,7 @@ impl<T> Strategy<T> for GeneralMultiStrategy<T>
 where
     T: Clone + SaveSliceToFile + Send + Sync + Debug,
 {
-    fn minimize(&self, stats: &mut Stats, settings: &Settings, mm: &mut dyn DataTraits<T>, rng: &mut ThreadRng) {
+    fn minimize(&self, stats: &mut Stats, settings: &Settings, mm: &mut dyn DataTraits<T>, rng: &mut StdRng) {
         minimize_internal(stats, settings, mm, rng);
 
         // After minimization to less than 5 elements, we try to remove all combinations of 2, 3, 4 elements
@@ -77,27 +80,35 @@ where
             }
             let new_data = rule.execute(stats, test_vec, mode, settings);
 
-            Some(new_data)
+            Some((rule, new_data))
         })
         .while_some()
         .collect::<Vec<_>>();
 
-    let tested_items = results.len() as u32;
-    let filtered_results = results.into_iter().flatten().collect::<Vec<_>>();
+    let cache_hits = results.iter().filter(|(_, result)| result.from_cache).count() as u32;
+    let tested_items = results.len() as u32 - cache_hits;
+    let filtered_results = results
+        .into_iter()
+        .filter_map(|(rule, result)| result.new_content.map(|new_content| (rule, new_content)))
+        .collect::<Vec<_>>();
 
-    let smallest_content = filtered_results.iter().min_by_key(|x| x.len());
-    if let Some(smallest_content) = smallest_content {
+    let smallest_content = filtered_results.iter().min_by_key(|(_, x)| x.len());
+    if let Some((_, smallest_content)) = smallest_content {
         mm.replace_vec(smallest_content.clone());
     }
     extend_results(
         smallest_content.is_some(),
         tested_items,
+        cache_hits,
         old_len,
         mm.len(),
         stats,
         mm.get_mode(),
         settings,
     );
+    if let Some((rule, _)) = smallest_content {
+        save_accepted_content(rule, mode, old_len, mm.get_vec(), stats, settings);
+    }
 
     if stopped.load(std::sync::atomic::Ordering::Relaxed) {
         return ProcessStatus::Stop;
@@ -105,7 +116,7 @@ where
     ProcessStatus::Continue
 }
 
-fn minimize_internal<T>(stats: &mut Stats, settings: &Settings, mm: &mut dyn DataTraits<T>, _rng: &mut ThreadRng)
+fn minimize_internal<T>(stats: &mut Stats, settings: &Settings, mm: &mut dyn DataTraits<T>, rng: &mut StdRng)
 where
     T: Clone + SaveSliceToFile + Send + Sync + Debug,
 {
@@ -117,7 +128,7 @@ where
             return;
         }
 
-        let from_start_end_rules = Rule::create_start_end_rule(mm.len(), iters, from_start);
+        let from_start_end_rules = Rule::create_start_end_rule(mm.len(), iters, from_start, rng);
 
         if execute_multi_rules(from_start_end_rules, stats, settings, mm, true) == ProcessStatus::Stop {
             return;
@@ -132,7 +143,18 @@ where
         let available_stats = stats.available();
         let to_use = available_stats.min((2 * *NUMBER_OF_THREADS) as u32);
 
-        let rules = (0..to_use).map(|_| get_random_rule(mm.len())).collect::<Vec<_>>();
+        // Seeds are derived from index of rule instead of worker thread, so generated rules not depend on rayon scheduling
+        let batch_seed: u64 = rng.gen();
+        let content_size = mm.len();
+        let rules = (0..to_use)
+            .into_par_iter()
+            .map(|idx| {
+                get_random_rule(
+                    content_size,
+                    &mut StdRng::seed_from_u64(batch_seed.wrapping_add(idx as u64)),
+                )
+            })
+            .collect::<Vec<_>>();
 
         if execute_multi_rules(rules, stats, settings, mm, true) == ProcessStatus::Stop {
             return;
@@ -140,19 +162,19 @@ where
     }
 }
 
-pub fn get_random_rule(content_size: usize) -> Rule {
+pub fn get_random_rule<R: Rng>(content_size: usize, rng: &mut R) -> Rule {
     let rules_weights = [
         (RuleType::RemoveFromStart, 2),
         (RuleType::RemoveFromEnd, 10),
         (RuleType::RemoveContinuousFromMiddle, 30),
         (RuleType::RemoveRandom, 10),
     ];
-    let chosen = RuleType::get_random_type(&rules_weights);
+    let chosen = RuleType::get_random_type(&rules_weights, rng);
 
     match chosen {
-        RuleType::RemoveFromStart => Rule::create_start_end_rule(content_size, 1, true)[0].clone(),
-        RuleType::RemoveFromEnd => Rule::create_start_end_rule(content_size, 1, false)[0].clone(),
-        RuleType::RemoveContinuousFromMiddle => Rule::create_continuous_rule(content_size),
-        RuleType::RemoveRandom => Rule::create_random_rule(content_size, None),
+        RuleType::RemoveFromStart => Rule::create_start_end_rule(content_size, 1, true, rng)[0].clone(),
+        RuleType::RemoveFromEnd => Rule::create_start_end_rule(content_size, 1, false, rng)[0].clone(),
+        RuleType::RemoveContinuousFromMiddle => Rule::create_continuous_rule(content_size, rng),
+        RuleType::RemoveRandom => Rule::create_random_rule(content_size, None, rng),
     }
 }
@@ -0,0 +1,57 @@
+use rand::rngs::StdRng;
+
+use crate::blocks::{count_levels, parse_units, BlockLayout};
+use crate::data_trait::MinimizationBlocks;
+use crate::minimize_with_strategy;
+use crate::settings::Settings;
+use crate::strategy::common::{check_if_exceeded_time, check_if_interrupted, ProcessStatus};
+use crate::Stats;
+
+// Hierarchical minimization(similar to HDD), which removes whole blocks of code level by level
+// How this works:
+// - Text is parsed into tree of blocks - statements, content of brackets and indented blocks
+// - At first, blocks from top level are minimized with chosen strategy, so e.g. whole functions are removed
+// - Then text is parsed again and blocks from next level are minimized(e.g. statements inside functions)
+// - Available attempts are equally split between levels found at start
+pub fn minimize_blocks(
+    stats: &mut Stats,
+    settings: &Settings,
+    initial_text: String,
+    rng: &mut StdRng,
+    max_attempts: u32,
+) -> String {
+    let mut text = initial_text;
+    let start_attempts = stats.current_iteration_count;
+    let initial_levels = count_levels(&parse_units(&text.chars().collect::<Vec<_>>()));
+
+    let mut level = 0;
+    loop {
+        if check_if_exceeded_time(settings) == ProcessStatus::Stop || check_if_interrupted() == ProcessStatus::Stop {
+            break;
+        }
+        let chars = text.chars().collect::<Vec<_>>();
+        let units = parse_units(&chars);
+        let (layout, blocks_number) = BlockLayout::new(&chars, &units, level);
+        if blocks_number == 0 {
+            break;
+        }
+
+        let level_attempts =
+            max_attempts.saturating_sub(start_attempts) as usize * (level + 1) / initial_levels.max(level + 1);
+        stats.max_attempts = start_attempts + level_attempts as u32;
+        if stats.available() == 0 {
+            break;
+        }
+        if settings.is_verbose_message_visible() {
+            println!("Minimizing {blocks_number} blocks at level {level}");
+        }
+
+        let mut mb = MinimizationBlocks::new(&layout, blocks_number);
+        minimize_with_strategy(stats, settings, &mut mb, rng);
+        text = mb.create_text(&layout);
+
+        level += 1;
+    }
+
+    text
+}
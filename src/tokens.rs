@@ -0,0 +1,107 @@
+use crate::data_trait::Token;
+
+// Splits text into identifiers, numbers and single punctuation characters
+// Whitespaces are not separate tokens, but are kept as separator of previous token, so they are preserved when saving
+pub fn split_into_tokens(text: &str) -> Vec<Token> {
+    let chars = text.chars().collect::<Vec<_>>();
+    let mut tokens: Vec<Token> = Vec::new();
+
+    let mut idx = 0;
+    while idx < chars.len() {
+        let character = chars[idx];
+        let token_end = if character.is_whitespace() {
+            let whitespace_end = find_end(&chars, idx, |character| character.is_whitespace());
+            let whitespace = chars[idx..whitespace_end].iter().collect::<String>();
+            match tokens.last_mut() {
+                Some(last_token) => last_token.separator.push_str(&whitespace),
+                // Whitespaces at start of text are kept in token without any text
+                None => tokens.push(Token {
+                    text: String::new(),
+                    separator: whitespace,
+                }),
+            }
+            idx = whitespace_end;
+            continue;
+        } else if character.is_ascii_digit() {
+            find_end(&chars, idx, |character| {
+                is_word_character(character) || character == '.'
+            })
+        } else if is_word_character(character) {
+            find_end(&chars, idx, is_word_character)
+        } else {
+            idx + 1
+        };
+
+        tokens.push(Token {
+            text: chars[idx..token_end].iter().collect(),
+            separator: String::new(),
+        });
+        idx = token_end;
+    }
+
+    tokens
+}
+
+pub fn tokens_to_string(tokens: &[Token]) -> String {
+    let mut text = String::new();
+    let mut previous_token: Option<&Token> = None;
+    for token in tokens {
+        // After removing token between two words, they would be joined into one, e.g. `int(x)` -> `intx)`
+        if let Some(previous_token) = previous_token {
+            if previous_token.separator.is_empty()
+                && previous_token.text.chars().last().is_some_and(is_word_character)
+                && token.text.chars().next().is_some_and(is_word_character)
+            {
+                text.push(' ');
+            }
+        }
+        text.push_str(&token.text);
+        text.push_str(&token.separator);
+        previous_token = Some(token);
+    }
+    text
+}
+
+fn is_word_character(character: char) -> bool {
+    character.is_alphanumeric() || character == '_'
+}
+
+fn find_end(chars: &[char], start: usize, predicate: impl Fn(char) -> bool) -> usize {
+    chars[start..]
+        .iter()
+        .position(|character| !predicate(*character))
+        .map_or(chars.len(), |position| start + position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_texts(text: &str) -> Vec<String> {
+        split_into_tokens(text).into_iter().map(|token| token.text).collect()
+    }
+
+    #[test]
+    fn test_split_into_tokens() {
+        assert_eq!(
+            token_texts("let abc_1 = foo(2.5e3, 0xFF);"),
+            vec!["let", "abc_1", "=", "foo", "(", "2.5e3", ",", "0xFF", ")", ";"]
+        );
+        assert_eq!(token_texts("a==b"), vec!["a", "=", "=", "b"]);
+        assert_eq!(token_texts("  x\n"), vec!["", "x"]);
+        assert!(token_texts("").is_empty());
+    }
+
+    #[test]
+    fn test_tokens_to_string() {
+        for text in ["let abc_1 = foo(2.5e3, 0xFF);", "  x\n", "a\tb\n\nc  ", "zażółć(gęślą)"] {
+            assert_eq!(tokens_to_string(&split_into_tokens(text)), text);
+        }
+
+        let mut tokens = split_into_tokens("int(x) + y");
+        tokens.remove(1);
+        assert_eq!(tokens_to_string(&tokens), "int x) + y");
+        tokens.remove(2);
+        assert_eq!(tokens_to_string(&tokens), "int x+ y");
+    }
+}
@@ -0,0 +1,113 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::{fs, process};
+
+use once_cell::sync::OnceCell;
+
+use crate::data_trait::Mode;
+use crate::rules::Rule;
+use crate::settings::{Settings, EXTENSION};
+use crate::Stats;
+
+const TRACE_LOG_FILE_NAME: &str = "trace.log";
+
+static TRACE: OnceCell<Trace> = OnceCell::new();
+
+struct Trace {
+    dir: PathBuf,
+    // Step number is taken under the same lock, so order of files and log lines is always the same
+    log: Mutex<TraceLog>,
+}
+
+struct TraceLog {
+    file: File,
+    next_step: u32,
+}
+
+// Step 0 is initial file, each next step is content accepted after executing single rule
+// With resumed session, steps are appended to already existing trace
+pub fn start_trace(settings: &Settings, initial_content: &[u8]) {
+    let Some(trace_dir) = &settings.trace_dir else {
+        return;
+    };
+    if let Err(e) = fs::create_dir_all(trace_dir) {
+        eprintln!("Error creating trace directory {trace_dir}, reason {e}");
+        process::exit(1);
+    }
+    let log_file = PathBuf::from(trace_dir).join(TRACE_LOG_FILE_NAME);
+    let previous_steps = if settings.resume {
+        fs::read_to_string(&log_file).map_or(0, |content| content.lines().count() as u32)
+    } else {
+        0
+    };
+    let file = match OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(settings.resume)
+        .truncate(!settings.resume)
+        .open(&log_file)
+    {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Error opening trace log {}, reason {}", log_file.display(), e);
+            process::exit(1);
+        }
+    };
+    TRACE
+        .set(Trace {
+            dir: PathBuf::from(trace_dir),
+            log: Mutex::new(TraceLog {
+                file,
+                next_step: previous_steps,
+            }),
+        })
+        .unwrap_or_else(|_| panic!("Trace started twice, which should not happen"));
+
+    if previous_steps == 0 {
+        save_trace_step(initial_content, "initial file");
+    }
+}
+
+pub fn save_trace_rule_step(
+    rule: &Rule,
+    mode: Mode,
+    length_before: usize,
+    length_after: usize,
+    content: &[u8],
+    stats: &Stats,
+) {
+    if TRACE.get().is_none() {
+        return;
+    }
+    save_trace_step(
+        content,
+        &format!(
+            "attempt {} | {mode} {length_before} -> {length_after} | {rule}",
+            stats.all_iterations
+        ),
+    );
+}
+
+fn save_trace_step(content: &[u8], description: &str) {
+    let Some(trace) = TRACE.get() else {
+        return;
+    };
+    let mut log = trace.log.lock().expect("Trace lock poisoned");
+    let file_name = format!(
+        "step_{:05}{}",
+        log.next_step,
+        EXTENSION.get().expect("Extension not set, but should be set")
+    );
+    let file = trace.dir.join(&file_name);
+    if let Err(e) = fs::write(&file, content) {
+        eprintln!("Error writing trace file {}, reason {}", file.display(), e);
+        process::exit(1);
+    }
+    if let Err(e) = writeln!(log.file, "{file_name} | {} bytes | {description}", content.len()) {
+        eprintln!("Error writing to trace log, reason {e}");
+        process::exit(1);
+    }
+    log.next_step += 1;
+}